@@ -0,0 +1,19 @@
+CREATE TABLE users (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    username TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    password_hash TEXT NOT NULL
+);
+
+-- Todos now belong to users, those of anonymous sessions are set aside until the user of the
+-- session registers or logs in, see `TodoRepository::adopt`
+ALTER TABLE todos RENAME TO anonymous_todos;
+
+CREATE TABLE todos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    done BOOLEAN NOT NULL DEFAULT FALSE,
+    version INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE INDEX todos_user_id ON todos (user_id);
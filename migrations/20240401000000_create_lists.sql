@@ -0,0 +1,29 @@
+CREATE TABLE lists (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    name TEXT NOT NULL
+);
+
+CREATE INDEX lists_user_id ON lists (user_id);
+
+-- The todos each user already has go to a first list
+INSERT INTO lists (user_id, name)
+SELECT DISTINCT user_id, 'Todo' FROM todos;
+
+CREATE TABLE list_todos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    list_id INTEGER NOT NULL REFERENCES lists (id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    done BOOLEAN NOT NULL DEFAULT FALSE,
+    version INTEGER NOT NULL DEFAULT 0
+);
+
+INSERT INTO list_todos (id, list_id, content, done, version)
+SELECT todos.id, lists.id, todos.content, todos.done, todos.version
+FROM todos JOIN lists ON lists.user_id = todos.user_id;
+
+DROP TABLE todos;
+
+ALTER TABLE list_todos RENAME TO todos;
+
+CREATE INDEX todos_list_id ON todos (list_id);
@@ -0,0 +1,22 @@
+-- Users access lists through their membership, `lists.user_id` only records who created the list
+CREATE TABLE list_members (
+    list_id INTEGER NOT NULL REFERENCES lists (id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    role TEXT NOT NULL CHECK (role IN ('owner', 'editor', 'viewer')),
+    PRIMARY KEY (list_id, user_id)
+);
+
+CREATE INDEX list_members_user_id ON list_members (user_id);
+
+INSERT INTO list_members (list_id, user_id, role)
+SELECT id, user_id, 'owner' FROM lists;
+
+CREATE TABLE list_invites (
+    token TEXT PRIMARY KEY,
+    list_id INTEGER NOT NULL REFERENCES lists (id) ON DELETE CASCADE,
+    role TEXT NOT NULL CHECK (role IN ('owner', 'editor', 'viewer')),
+    -- Unix timestamp, in seconds
+    expires_at INTEGER NOT NULL
+);
+
+CREATE INDEX list_invites_list_id ON list_invites (list_id);
@@ -0,0 +1,9 @@
+ALTER TABLE todos ADD COLUMN due_date TEXT;
+ALTER TABLE todos ADD COLUMN due_time TEXT;
+ALTER TABLE todos ADD COLUMN time_zone TEXT;
+-- Unix timestamp, in seconds, of the due date in its time zone
+ALTER TABLE todos ADD COLUMN due_at INTEGER;
+-- Whether the reminder of the current due date was sent
+ALTER TABLE todos ADD COLUMN reminded BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX todos_due_at ON todos (due_at) WHERE NOT reminded;
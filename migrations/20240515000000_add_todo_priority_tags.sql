@@ -0,0 +1,5 @@
+ALTER TABLE todos ADD COLUMN priority TEXT NOT NULL DEFAULT 'normal'
+    CHECK (priority IN ('low', 'normal', 'high'));
+
+-- Separated by spaces, tags cannot contain any
+ALTER TABLE todos ADD COLUMN tags TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,4 @@
+-- Subtasks are deleted along with their parent
+ALTER TABLE todos ADD COLUMN parent_id INTEGER REFERENCES todos (id) ON DELETE CASCADE;
+
+CREATE INDEX todos_parent_id ON todos (parent_id);
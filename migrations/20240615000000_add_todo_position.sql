@@ -0,0 +1,7 @@
+-- Todos are ordered by position among their siblings, a todo moved between two others takes the
+-- middle of their positions
+ALTER TABLE todos ADD COLUMN position REAL NOT NULL DEFAULT 0;
+
+UPDATE todos SET position = id;
+
+CREATE INDEX todos_list_id_position ON todos (list_id, position);
@@ -0,0 +1,5 @@
+-- Unix timestamps, in seconds. The todos created before are taken as created and updated now.
+ALTER TABLE todos ADD COLUMN created_at INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE todos ADD COLUMN updated_at INTEGER NOT NULL DEFAULT 0;
+
+UPDATE todos SET created_at = strftime('%s', 'now'), updated_at = strftime('%s', 'now');
@@ -0,0 +1,5 @@
+-- Deleted todos stay in the trash until purged, with the Unix timestamp they were deleted at, in
+-- milliseconds. A todo and the subtasks deleted along with it share the same timestamp.
+ALTER TABLE todos ADD COLUMN deleted_at INTEGER;
+
+CREATE INDEX todos_deleted_at ON todos (deleted_at) WHERE deleted_at IS NOT NULL;
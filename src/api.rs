@@ -0,0 +1,494 @@
+//! JSON API, sharing its storage with the HTML pages.
+
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use chrono::Utc;
+use serde::{Deserialize, Deserializer, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    auth::AuthUser,
+    errors::ApplicationError,
+    events::TodoChange,
+    extract::{Json, Path, Query},
+    lists::{CurrentList, ListForm},
+    members::Role,
+    todos::{self, CreateTodoForm, Due, GetTodosForm, Priority, Todo},
+    trash, validation, AppState,
+};
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/lists",
+    responses(
+        (status = 200, description = "The lists of the user", body = [TodoList]),
+        (status = 401, description = "Not logged in", body = ErrorBody)
+    )
+)]
+pub async fn list_lists(
+    State(state): State<AppState>,
+    user: AuthUser,
+) -> Result<impl IntoResponse, ApplicationError> {
+    Ok(axum::Json(state.lists.list(user.id).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/lists/{list_id}",
+    params(("list_id" = i64, Path, description = "Id of the list")),
+    responses(
+        (status = 200, description = "The list", body = TodoList),
+        (status = 401, description = "Not logged in", body = ErrorBody),
+        (status = 404, description = "No such list", body = ErrorBody)
+    )
+)]
+pub async fn get_list(CurrentList(list): CurrentList) -> impl IntoResponse {
+    axum::Json(list)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/lists",
+    request_body = ListForm,
+    responses(
+        (status = 201, description = "The new list", body = TodoList),
+        (status = 401, description = "Not logged in", body = ErrorBody),
+        (status = 422, description = "Invalid name", body = ErrorBody)
+    )
+)]
+pub async fn create_list(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Json(form): Json<ListForm>,
+) -> Result<impl IntoResponse, ApplicationError> {
+    let name = validation::list_name(&form.name).map_err(ApplicationError::InvalidInput)?;
+    let list = state.lists.create(user.id, &name).await?;
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION, format!("/api/v1/lists/{}", list.id))],
+        axum::Json(list),
+    ))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/v1/lists/{list_id}",
+    params(("list_id" = i64, Path, description = "Id of the list")),
+    request_body = ListForm,
+    responses(
+        (status = 200, description = "The renamed list", body = TodoList),
+        (status = 403, description = "Only owners rename the list", body = ErrorBody),
+        (status = 401, description = "Not logged in", body = ErrorBody),
+        (status = 404, description = "No such list", body = ErrorBody),
+        (status = 422, description = "Invalid name", body = ErrorBody)
+    )
+)]
+pub async fn rename_list(
+    State(state): State<AppState>,
+    user: AuthUser,
+    CurrentList(list): CurrentList,
+    Json(form): Json<ListForm>,
+) -> Result<impl IntoResponse, ApplicationError> {
+    list.require(Role::Owner)?;
+    let name = validation::list_name(&form.name).map_err(ApplicationError::InvalidInput)?;
+    let list = state
+        .lists
+        .rename(user.id, list.id, &name)
+        .await?
+        .ok_or(ApplicationError::NotFound)?;
+    Ok(axum::Json(list))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/lists/{list_id}",
+    params(("list_id" = i64, Path, description = "Id of the list")),
+    responses(
+        (status = 204, description = "The list and its todos were deleted"),
+        (status = 403, description = "Only owners delete the list", body = ErrorBody),
+        (status = 401, description = "Not logged in", body = ErrorBody),
+        (status = 404, description = "No such list", body = ErrorBody)
+    )
+)]
+pub async fn delete_list(
+    State(state): State<AppState>,
+    user: AuthUser,
+    CurrentList(list): CurrentList,
+) -> Result<impl IntoResponse, ApplicationError> {
+    list.require(Role::Owner)?;
+    state
+        .lists
+        .delete(user.id, list.id)
+        .await?
+        .ok_or(ApplicationError::NotFound)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TodoResponse {
+    id: i64,
+    #[serde(flatten)]
+    todo: Todo,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/lists/{list_id}/todos",
+    params(("list_id" = i64, Path, description = "Id of the list"), GetTodosForm),
+    responses(
+        (status = 200, description = "The matching todos", body = [TodoResponse]),
+        (status = 400, description = "Invalid filter", body = ErrorBody),
+        (status = 404, description = "No such list", body = ErrorBody),
+        (status = 401, description = "Not logged in", body = ErrorBody)
+    )
+)]
+pub async fn list_todos(
+    State(state): State<AppState>,
+    CurrentList(list): CurrentList,
+    Query(query): Query<GetTodosForm>,
+) -> Result<impl IntoResponse, ApplicationError> {
+    let todos = query.apply(state.todos.list(list.id).await?, Utc::now());
+    Ok(axum::Json(
+        todos
+            .into_iter()
+            .map(|(id, todo)| TodoResponse { id, todo })
+            .collect::<Vec<_>>(),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/lists/{list_id}/todos/{id}",
+    params(
+        ("list_id" = i64, Path, description = "Id of the list"),
+        ("id" = i64, Path, description = "Id of the todo")
+    ),
+    responses(
+        (status = 200, description = "The todo", body = TodoResponse),
+        (status = 404, description = "No such list or todo", body = ErrorBody),
+        (status = 401, description = "Not logged in", body = ErrorBody)
+    )
+)]
+pub async fn get_todo(
+    State(state): State<AppState>,
+    CurrentList(list): CurrentList,
+    Path((_, id)): Path<(i64, i64)>,
+) -> Result<impl IntoResponse, ApplicationError> {
+    let todo = state
+        .todos
+        .get(list.id, id)
+        .await?
+        .ok_or(ApplicationError::NotFound)?;
+    Ok(axum::Json(TodoResponse { id, todo }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/lists/{list_id}/todos",
+    params(("list_id" = i64, Path, description = "Id of the list")),
+    request_body = CreateTodoForm,
+    responses(
+        (status = 201, description = "The new todo", body = TodoResponse),
+        (status = 403, description = "Viewers cannot change the todos", body = ErrorBody),
+        (status = 404, description = "No such list or parent todo", body = ErrorBody),
+        (status = 422, description = "Invalid content", body = ErrorBody),
+        (status = 401, description = "Not logged in", body = ErrorBody)
+    )
+)]
+pub async fn create_todo(
+    State(state): State<AppState>,
+    CurrentList(list): CurrentList,
+    Json(form): Json<CreateTodoForm>,
+) -> Result<impl IntoResponse, ApplicationError> {
+    list.require(Role::Editor)?;
+    let content =
+        validation::todo_content(&form.content).map_err(ApplicationError::InvalidInput)?;
+    let (id, todo, related) = todos::add_todo(&state, list.id, content, form.parent_id).await?;
+    state
+        .events
+        .publish(list.id, None, TodoChange::Created(id, todo.clone()));
+    todos::publish_updates(&state, list.id, &None, &related);
+    Ok((
+        StatusCode::CREATED,
+        [(
+            header::LOCATION,
+            format!("/api/v1/lists/{}/todos/{id}", list.id),
+        )],
+        axum::Json(TodoResponse { id, todo }),
+    ))
+}
+
+/// Fields left out are kept as is. Without a `version`, the update applies whatever the current
+/// version is.
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateTodoRequest {
+    content: Option<String>,
+    done: Option<bool>,
+    version: Option<i64>,
+    /// `null` removes the due date.
+    #[serde(default, deserialize_with = "present")]
+    #[schema(value_type = Option<Due>)]
+    due: Option<Option<Due>>,
+    priority: Option<Priority>,
+    /// Replace all the tags of the todo.
+    tags: Option<Vec<String>>,
+}
+
+/// Tells a field set to `null`, `Some(None)`, from a missing one, `None`.
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::deserialize(deserializer).map(Some)
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/v1/lists/{list_id}/todos/{id}",
+    params(
+        ("list_id" = i64, Path, description = "Id of the list"),
+        ("id" = i64, Path, description = "Id of the todo")
+    ),
+    request_body = UpdateTodoRequest,
+    responses(
+        (status = 200, description = "The updated todo", body = TodoResponse),
+        (status = 403, description = "Viewers cannot change the todos", body = ErrorBody),
+        (status = 404, description = "No such list or todo", body = ErrorBody),
+        (status = 409, description = "The todo changed since `version`", body = ErrorBody),
+        (status = 422, description = "Invalid content or tags", body = ErrorBody),
+        (status = 401, description = "Not logged in", body = ErrorBody)
+    )
+)]
+pub async fn update_todo(
+    State(state): State<AppState>,
+    CurrentList(list): CurrentList,
+    Path((_, id)): Path<(i64, i64)>,
+    Json(request): Json<UpdateTodoRequest>,
+) -> Result<impl IntoResponse, ApplicationError> {
+    list.require(Role::Editor)?;
+    let mut todo = state
+        .todos
+        .get(list.id, id)
+        .await?
+        .ok_or(ApplicationError::NotFound)?;
+    let was_done = todo.done;
+    if let Some(content) = request.content {
+        todo.content =
+            validation::todo_content(&content).map_err(ApplicationError::InvalidInput)?;
+    }
+    if let Some(done) = request.done {
+        todo.done = done;
+    }
+    if let Some(due) = request.due {
+        todo.due = due;
+    }
+    if let Some(priority) = request.priority {
+        todo.priority = priority;
+    }
+    if let Some(tags) = request.tags {
+        todo.tags = validation::tags(tags.iter().map(String::as_str))
+            .map_err(ApplicationError::InvalidInput)?;
+    }
+    if let Some(version) = request.version {
+        todo.version = version;
+    }
+
+    let (mut todo, mut cascaded) = state
+        .todos
+        .update(list.id, id, todo)
+        .await?
+        .ok_or(ApplicationError::NotFound)?;
+    let mut related = Vec::new();
+    if todo.done != was_done {
+        cascaded.push(id);
+        related = todos::changed_rows(&state, list.id, todo.parent_id, cascaded).await?;
+        if let Some(index) = related.iter().position(|(related_id, _)| *related_id == id) {
+            todo = related.remove(index).1;
+        }
+    }
+    state
+        .events
+        .publish(list.id, None, TodoChange::Updated(id, todo.clone()));
+    todos::publish_updates(&state, list.id, &None, &related);
+    Ok(axum::Json(TodoResponse { id, todo }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/lists/{list_id}/todos/{id}",
+    params(
+        ("list_id" = i64, Path, description = "Id of the list"),
+        ("id" = i64, Path, description = "Id of the todo")
+    ),
+    responses(
+        (status = 204, description = "The todo and its subtasks were moved to the trash"),
+        (status = 403, description = "Viewers cannot change the todos", body = ErrorBody),
+        (status = 404, description = "No such list or todo", body = ErrorBody),
+        (status = 401, description = "Not logged in", body = ErrorBody)
+    )
+)]
+pub async fn delete_todo(
+    State(state): State<AppState>,
+    CurrentList(list): CurrentList,
+    Path((_, id)): Path<(i64, i64)>,
+) -> Result<impl IntoResponse, ApplicationError> {
+    list.require(Role::Editor)?;
+    let todo = state
+        .todos
+        .delete(list.id, id)
+        .await?
+        .ok_or(ApplicationError::NotFound)?;
+    let related = todos::changed_rows(&state, list.id, todo.parent_id, Vec::new()).await?;
+    state.events.publish(list.id, None, TodoChange::Deleted(id));
+    todos::publish_updates(&state, list.id, &None, &related);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/lists/{list_id}/todos/{id}/restore",
+    params(
+        ("list_id" = i64, Path, description = "Id of the list"),
+        ("id" = i64, Path, description = "Id of the todo")
+    ),
+    responses(
+        (status = 200, description = "The todo restored from the trash, with its subtasks deleted along with it", body = TodoResponse),
+        (status = 403, description = "Viewers cannot change the todos", body = ErrorBody),
+        (status = 404, description = "No such list or todo in the trash", body = ErrorBody),
+        (status = 401, description = "Not logged in", body = ErrorBody)
+    )
+)]
+pub async fn restore_todo(
+    State(state): State<AppState>,
+    CurrentList(list): CurrentList,
+    Path((_, id)): Path<(i64, i64)>,
+) -> Result<impl IntoResponse, ApplicationError> {
+    list.require(Role::Editor)?;
+    let todo = trash::restore(&state, list.id, id).await?;
+    state.events.publish(list.id, None, TodoChange::Reload);
+    Ok(axum::Json(TodoResponse { id, todo }))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        extract::State,
+        http::{header, StatusCode},
+        response::{IntoResponse, Response},
+    };
+    use serde_json::{json, Value};
+
+    use super::{create_todo, get_todo, update_todo, UpdateTodoRequest};
+    use crate::{
+        extract::{Json, Path},
+        members::Role,
+        todos::{
+            tests::{body, list, state},
+            CreateTodoForm,
+        },
+        AppState,
+    };
+
+    async fn create(state: &AppState, role: Role, content: &str) -> Response {
+        let form = CreateTodoForm {
+            content: content.to_owned(),
+            parent_id: None,
+        };
+        create_todo(State(state.clone()), list(role), Json(form))
+            .await
+            .into_response()
+    }
+
+    async fn update(state: &AppState, id: i64, request: Value) -> Response {
+        let request: UpdateTodoRequest = serde_json::from_value(request).unwrap();
+        update_todo(
+            State(state.clone()),
+            list(Role::Editor),
+            Path((1, id)),
+            Json(request),
+        )
+        .await
+        .into_response()
+    }
+
+    async fn json(response: Response) -> Value {
+        serde_json::from_str(&body(response).await).unwrap()
+    }
+
+    #[tokio::test]
+    async fn create_todo_returns_it_as_stored() {
+        let state = state().await;
+        let response = create(&state, Role::Editor, " Buy milk ").await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(
+            response.headers()[header::LOCATION],
+            "/api/v1/lists/1/todos/1"
+        );
+        let created = json(response).await;
+        assert_eq!(created["content"], "Buy milk");
+
+        let response = get_todo(State(state.clone()), list(Role::Viewer), Path((1, 1)))
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json(response).await, created);
+    }
+
+    #[tokio::test]
+    async fn viewers_cannot_change_the_todos() {
+        let state = state().await;
+        let response = create(&state, Role::Viewer, "Buy milk").await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(state.todos.list(1).await.unwrap().is_empty());
+
+        create(&state, Role::Editor, "Buy milk").await;
+        let request = serde_json::from_value(json!({ "done": true })).unwrap();
+        let response = update_todo(
+            State(state.clone()),
+            list(Role::Viewer),
+            Path((1, 1)),
+            Json(request),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn update_todo_checks_the_version() {
+        let state = state().await;
+        create(&state, Role::Editor, "Buy milk").await;
+
+        let response = update(&state, 1, json!({ "done": true, "version": 0 })).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json(response).await["version"], 1);
+
+        let response = update(&state, 1, json!({ "done": false, "version": 0 })).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        // Without a version, whatever the current one is
+        let response = update(&state, 1, json!({ "done": false })).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = update(&state, 2, json!({ "done": true })).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn update_todo_removes_the_due_date_set_to_null_only() {
+        let state = state().await;
+        create(&state, Role::Editor, "Buy milk").await;
+        let due = json!({ "date": "2024-06-01", "time": null, "time_zone": "UTC" });
+
+        let response = update(&state, 1, json!({ "due": due })).await;
+        assert_eq!(json(response).await["due"]["date"], "2024-06-01");
+        let response = update(&state, 1, json!({ "content": "Buy oat milk" })).await;
+        assert_eq!(json(response).await["due"]["date"], "2024-06-01");
+
+        let response = update(&state, 1, json!({ "due": null })).await;
+        assert_eq!(json(response).await["due"], Value::Null);
+    }
+}
@@ -0,0 +1,418 @@
+//! User accounts: registration, login and logout, and the [`AuthUser`] extractor.
+
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use argon2::{
+    password_hash::{rand_core::OsRng, SaltString},
+    Argon2, PasswordHash, PasswordHasher, PasswordVerifier,
+};
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, State},
+    http::{request::Parts, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use leptos::*;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tower_sessions::{session::Id, Session, SessionStore};
+use tower_sessions_sqlx_store::SqliteStore;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    components::Page,
+    errors::ApplicationError,
+    extract::{Form, Query},
+    lists,
+    repository::TodoRepository,
+    AppState,
+};
+
+const USER_KEY: &str = "user";
+/// Id of the todos sessions had before user accounts, see [`log_in`].
+const OWNER_KEY: &str = "owner";
+
+#[derive(sqlx::FromRow)]
+pub struct User {
+    pub id: i64,
+    pub username: String,
+    pub password_hash: String,
+}
+
+/// User logged in the session. Rejects with [`ApplicationError::Unauthorized`] otherwise.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AuthUser {
+    pub id: i64,
+    pub username: String,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = ApplicationError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let session = Session::from_request_parts(parts, state)
+            .await
+            .map_err(|_| ApplicationError::SessionUnavailable)?;
+        session
+            .get(USER_KEY)
+            .await?
+            .ok_or(ApplicationError::Unauthorized)
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CredentialsForm {
+    username: String,
+    password: String,
+    /// Page to go back to once logged in.
+    #[serde(default)]
+    next: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NextQuery {
+    /// Page to go back to once logged in.
+    #[serde(default)]
+    next: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/login",
+    params(NextQuery),
+    responses((status = 200, description = "The login page", content_type = "text/html"))
+)]
+pub async fn login_page(Query(query): Query<NextQuery>) -> impl IntoResponse {
+    let next = local_path(&query.next);
+    Html(leptos::ssr::render_to_string(move || view! { <LoginPage next/> }).into_owned())
+}
+
+#[utoipa::path(
+    post,
+    path = "/login",
+    request_body(content = CredentialsForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Logged in, redirects to the todo list"),
+        (status = 401, description = "The login page with an error", content_type = "text/html")
+    )
+)]
+pub async fn login(
+    State(state): State<AppState>,
+    session: Session,
+    Form(form): Form<CredentialsForm>,
+) -> Result<Response, ApplicationError> {
+    let user = state.users.find_by_username(form.username.trim()).await?;
+    let user = match user {
+        Some(user) if verify_password(form.password, user.password_hash.clone()).await? => user,
+        _ => {
+            let page = leptos::ssr::render_to_string(move || {
+                view! {
+                    <LoginPage
+                        username=form.username
+                        next=local_path(&form.next)
+                        error="Invalid username or password".to_owned()
+                    />
+                }
+            });
+            return Ok((StatusCode::UNAUTHORIZED, Html(page.into_owned())).into_response());
+        }
+    };
+
+    log_in(&state, &session, user).await?;
+    Ok(Redirect::to(&local_path(&form.next)).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/register",
+    params(NextQuery),
+    responses((status = 200, description = "The registration page", content_type = "text/html"))
+)]
+pub async fn register_page(Query(query): Query<NextQuery>) -> impl IntoResponse {
+    let next = local_path(&query.next);
+    Html(leptos::ssr::render_to_string(move || view! { <RegisterPage next/> }).into_owned())
+}
+
+#[utoipa::path(
+    post,
+    path = "/register",
+    request_body(content = CredentialsForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Account created and logged in, redirects to the todo list"),
+        (status = 422, description = "The registration page with an error", content_type = "text/html")
+    )
+)]
+pub async fn register(
+    State(state): State<AppState>,
+    session: Session,
+    Form(form): Form<CredentialsForm>,
+) -> Result<Response, ApplicationError> {
+    let username = form.username.trim().to_owned();
+    let mut error = validate_credentials(&username, &form.password).err();
+    if error.is_none() {
+        let password_hash = hash_password(form.password).await?;
+        match state.users.create(&username, &password_hash).await? {
+            Some(user) => {
+                log_in(&state, &session, user).await?;
+                return Ok(Redirect::to(&local_path(&form.next)).into_response());
+            }
+            None => error = Some("This username is already taken".to_owned()),
+        }
+    }
+
+    let page = leptos::ssr::render_to_string(move || {
+        view! {
+            <RegisterPage
+                username
+                next=local_path(&form.next)
+                error=error.unwrap_or_default()
+            />
+        }
+    });
+    Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(page.into_owned())).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/logout",
+    responses((status = 303, description = "Logged out, redirects to the login page"))
+)]
+pub async fn logout(session: Session) -> Result<impl IntoResponse, ApplicationError> {
+    session.flush().await?;
+    Ok(Redirect::to("/login"))
+}
+
+/// Renews the session id when logging in, so a session id planted before cannot be reused.
+///
+/// The todos the session had before user accounts go to the first list of the user.
+async fn log_in(state: &AppState, session: &Session, user: User) -> Result<(), ApplicationError> {
+    if let Some(owner) = session.get::<String>(OWNER_KEY).await? {
+        let list = lists::first_list(state, user.id).await?;
+        state.todos.adopt(&owner, list.id).await?;
+        session.remove::<String>(OWNER_KEY).await?;
+    }
+    session.cycle_id().await?;
+    session
+        .insert(
+            USER_KEY,
+            AuthUser {
+                id: user.id,
+                username: user.username,
+            },
+        )
+        .await?;
+    Ok(())
+}
+
+/// Deletes the todos set aside for the anonymous sessions which expired before their user logged
+/// in every `interval`, until none are left: sessions no longer get any.
+pub async fn drop_expired_todos(
+    todos: Arc<dyn TodoRepository>,
+    sessions: SqliteStore,
+    pool: SqlitePool,
+    interval: Duration,
+) {
+    let mut ticks = tokio::time::interval(interval);
+    loop {
+        ticks.tick().await;
+        match drop_expired_owners(todos.as_ref(), &sessions, &pool).await {
+            Ok(None) => return,
+            Ok(Some(0)) => {}
+            Ok(Some(dropped)) => tracing::info!("dropped {dropped} todos of expired sessions"),
+            Err(e) => tracing::error!("cannot drop the todos of expired sessions: {e:#}"),
+        }
+    }
+}
+
+/// How many todos were dropped, `None` when no session has any left.
+async fn drop_expired_owners(
+    todos: &dyn TodoRepository,
+    sessions: &SqliteStore,
+    pool: &SqlitePool,
+) -> anyhow::Result<Option<u64>> {
+    let owners = todos.anonymous_owners().await?;
+    if owners.is_empty() {
+        return Ok(None);
+    }
+    // The store only loads the sessions which have not expired
+    let ids: Vec<String> = sqlx::query_scalar("SELECT id FROM tower_sessions")
+        .fetch_all(pool)
+        .await?;
+    let mut live = HashSet::new();
+    for id in ids {
+        let Ok(id) = id.parse::<Id>() else {
+            continue;
+        };
+        if let Some(record) = sessions.load(&id).await? {
+            if let Some(owner) = record.data.get(OWNER_KEY).and_then(|owner| owner.as_str()) {
+                live.insert(owner.to_owned());
+            }
+        }
+    }
+    let mut dropped = 0;
+    for owner in owners.iter().filter(|owner| !live.contains(*owner)) {
+        dropped += todos.drop_anonymous(owner).await?;
+    }
+    Ok(Some(dropped))
+}
+
+/// `next` when it is a path of this site, `/` otherwise, so the login page cannot be used to
+/// send users elsewhere. Only plain paths are kept, they need no escaping in the query string.
+fn local_path(next: &str) -> String {
+    let plain = next
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "/-_".contains(c));
+    if plain && next.starts_with('/') && !next.starts_with("//") {
+        next.to_owned()
+    } else {
+        "/".to_owned()
+    }
+}
+
+fn validate_credentials(username: &str, password: &str) -> Result<(), String> {
+    if !(3..=32).contains(&username.chars().count()) {
+        return Err("The username must be between 3 and 32 characters long".to_owned());
+    }
+    if !username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+    {
+        return Err("The username can only contain letters, digits, '-', '_' and '.'".to_owned());
+    }
+    if !(8..=128).contains(&password.chars().count()) {
+        return Err("The password must be between 8 and 128 characters long".to_owned());
+    }
+    Ok(())
+}
+
+/// Hashing is deliberately slow, so it runs off the async workers.
+async fn hash_password(password: String) -> anyhow::Result<String> {
+    tokio::task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|e| anyhow::anyhow!(e))?;
+        Ok(hash.to_string())
+    })
+    .await?
+}
+
+async fn verify_password(password: String, password_hash: String) -> anyhow::Result<bool> {
+    tokio::task::spawn_blocking(move || {
+        let password_hash = PasswordHash::new(&password_hash).map_err(|e| anyhow::anyhow!(e))?;
+        Ok(Argon2::default()
+            .verify_password(password.as_bytes(), &password_hash)
+            .is_ok())
+    })
+    .await?
+}
+
+#[component]
+fn LoginPage(
+    #[prop(optional)] username: String,
+    next: String,
+    #[prop(optional)] error: String,
+) -> impl IntoView {
+    let register = format!("/register?next={next}");
+    view! {
+        <CredentialsPage title="Log in" action="/login" username next error>
+            <a class="underline" href=register>
+                No account yet? Register
+            </a>
+        </CredentialsPage>
+    }
+}
+
+#[component]
+fn RegisterPage(
+    #[prop(optional)] username: String,
+    next: String,
+    #[prop(optional)] error: String,
+) -> impl IntoView {
+    let login = format!("/login?next={next}");
+    view! {
+        <CredentialsPage title="Register" action="/register" username next error>
+            <a class="underline" href=login>
+                Already registered? Log in
+            </a>
+        </CredentialsPage>
+    }
+}
+
+#[component]
+fn CredentialsPage(
+    title: &'static str,
+    action: &'static str,
+    username: String,
+    next: String,
+    error: String,
+    children: Children,
+) -> impl IntoView {
+    view! {
+        <Page>
+            <h1 class="text-3xl">TodoMVC</h1>
+            <h2 class="text-xl">{title}</h2>
+            <form class="flex flex-col gap-2" method="post" action=action>
+                <input type="text" name="username" placeholder="Username" value=username required/>
+                <input type="password" name="password" placeholder="Password" required/>
+                <input type="hidden" name="next" value=next/>
+                {(!error.is_empty()).then(|| view! { <p class="text-red-700">{error}</p> })}
+                <button class="bg-teal-200 rounded-md p-2" type="submit">
+                    {title}
+                </button>
+            </form>
+            {children()}
+        </Page>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use tower_sessions::{
+        cookie::time::{Duration, OffsetDateTime},
+        session::{Id, Record},
+        SessionStore,
+    };
+    use tower_sessions_sqlx_store::SqliteStore;
+
+    use super::{drop_expired_owners, OWNER_KEY};
+    use crate::repository::{connect, SqliteTodoRepository, TodoRepository};
+
+    #[tokio::test]
+    async fn drops_the_todos_of_expired_sessions_only() {
+        let pool = connect("sqlite::memory:").await.unwrap();
+        let sessions = SqliteStore::new(pool.clone());
+        sessions.migrate().await.unwrap();
+        for owner in ["live", "expired", "expired"] {
+            sqlx::query("INSERT INTO anonymous_todos (owner, content) VALUES (?, 'Todo')")
+                .bind(owner)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        for (owner, expiry) in [
+            ("live", Duration::hours(1)),
+            ("expired", -Duration::hours(1)),
+        ] {
+            let record = Record {
+                id: Id::default(),
+                data: HashMap::from([(OWNER_KEY.to_owned(), owner.into())]),
+                expiry_date: OffsetDateTime::now_utc() + expiry,
+            };
+            sessions.save(&record).await.unwrap();
+        }
+        let todos = SqliteTodoRepository::new(pool.clone());
+
+        let dropped = drop_expired_owners(&todos, &sessions, &pool).await.unwrap();
+        assert_eq!(dropped, Some(2));
+        assert_eq!(todos.anonymous_owners().await.unwrap(), ["live"]);
+
+        todos.drop_anonymous("live").await.unwrap();
+        let dropped = drop_expired_owners(&todos, &sessions, &pool).await.unwrap();
+        assert_eq!(dropped, None);
+    }
+}
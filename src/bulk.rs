@@ -0,0 +1,461 @@
+//! Actions on many todos at once: the todos selected in the list page are completed, reopened,
+//! deleted, tagged or moved to another list in a single request, applied to all of them or none.
+//! The whole list can also be completed, or reopened, and cleared of its done todos.
+//!
+//! Every action answers with the todos of the list page, rendered with its filters and order.
+
+use std::collections::{HashMap, HashSet};
+
+use axum::{
+    extract::State,
+    response::{Html, IntoResponse},
+};
+use chrono::Utc;
+use leptos::*;
+use serde::{de, Deserialize, Deserializer};
+use utoipa::ToSchema;
+
+use crate::{
+    auth::AuthUser,
+    errors::ApplicationError,
+    events::{ClientId, TodoChange},
+    extract::Form,
+    footer::TodoFooter,
+    lists::{CurrentList, TodoList},
+    members::Role,
+    todos::{GetTodosForm, Todo, TodoPage},
+    trash::UndoToast,
+    validation, AppState,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, ToSchema)]
+pub enum BulkAction {
+    Complete,
+    Uncomplete,
+    /// Moves the todos to the trash.
+    Delete,
+    /// Adds `new_tag` to the todos.
+    Tag,
+    /// Moves the todos to the end of the list `to_list`.
+    Move,
+    /// Takes the todos out of the trash, to undo `Delete`.
+    Restore,
+}
+
+impl BulkAction {
+    /// The actions offered for the selected todos, restoring them is offered by the undo toast.
+    const ALL: [BulkAction; 5] = [
+        BulkAction::Complete,
+        BulkAction::Uncomplete,
+        BulkAction::Delete,
+        BulkAction::Tag,
+        BulkAction::Move,
+    ];
+
+    /// Value of the action in forms.
+    fn value(self) -> &'static str {
+        match self {
+            BulkAction::Complete => "Complete",
+            BulkAction::Uncomplete => "Uncomplete",
+            BulkAction::Delete => "Delete",
+            BulkAction::Tag => "Tag",
+            BulkAction::Move => "Move",
+            BulkAction::Restore => "Restore",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            BulkAction::Complete => "Mark as done",
+            BulkAction::Uncomplete => "Mark as not done",
+            BulkAction::Delete => "Delete",
+            BulkAction::Tag => "Add tag",
+            BulkAction::Move => "Move to list",
+            BulkAction::Restore => "Restore",
+        }
+    }
+}
+
+/// The action, the todos selected and the filters of the list page, which come along to render
+/// the todos again.
+#[derive(Deserialize, ToSchema)]
+pub struct BulkForm {
+    action: BulkAction,
+    /// Ids of the selected todos, separated by commas.
+    #[serde(deserialize_with = "comma_separated")]
+    #[schema(value_type = String, example = "1,2,3")]
+    ids: Vec<i64>,
+    #[serde(default)]
+    new_tag: String,
+    #[serde(default, deserialize_with = "blank_as_none")]
+    to_list: Option<i64>,
+    #[serde(flatten)]
+    filters: GetTodosForm,
+}
+
+/// Reads ids separated by commas.
+fn comma_separated<'de, D>(deserializer: D) -> Result<Vec<i64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    String::deserialize(deserializer)?
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| id.parse().map_err(de::Error::custom))
+        .collect()
+}
+
+/// Reads an empty id, sent for the placeholder option of a select, as a missing one.
+fn blank_as_none<'de, D>(deserializer: D) -> Result<Option<i64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(value) if !value.trim().is_empty() => {
+            value.trim().parse().map(Some).map_err(de::Error::custom)
+        }
+        _ => Ok(None),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{list_id}/todos/bulk",
+    params(("list_id" = i64, Path, description = "Id of the list")),
+    request_body(content = BulkForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "The matching todo rows, after the action, with a toast to undo a deletion out of band", content_type = "text/html"),
+        (status = 403, description = "Viewers cannot change the todos, nor move them to a list they only view", content_type = "text/html"),
+        (status = 404, description = "No such list", content_type = "text/html"),
+        (status = 409, description = "One of the todos changed meanwhile, none was changed", content_type = "text/html"),
+        (status = 422, description = "No todo selected, invalid tag or no list to move the todos to", content_type = "text/html")
+    )
+)]
+pub async fn bulk_action(
+    State(state): State<AppState>,
+    user: AuthUser,
+    CurrentList(list): CurrentList,
+    ClientId(client): ClientId,
+    Form(form): Form<BulkForm>,
+) -> Result<impl IntoResponse, ApplicationError> {
+    list.require(Role::Editor)?;
+    if form.ids.is_empty() {
+        return Err(ApplicationError::InvalidInput(
+            "Select some todos first".to_owned(),
+        ));
+    }
+
+    let todos = state.todos.list(list.id).await?;
+    let mut deleted = Vec::new();
+    match form.action {
+        BulkAction::Complete | BulkAction::Uncomplete => {
+            let done = form.action == BulkAction::Complete;
+            let changed = with_done(todos, &form.ids, done);
+            state.todos.update_many(list.id, changed).await?;
+        }
+        BulkAction::Tag => {
+            let tag = validation::tags([form.new_tag.as_str()])
+                .map_err(ApplicationError::InvalidInput)?;
+            if tag.is_empty() {
+                return Err(ApplicationError::InvalidInput(
+                    "Enter the tag to add".to_owned(),
+                ));
+            }
+            let selected: HashSet<i64> = form.ids.iter().copied().collect();
+            let mut changed = Vec::new();
+            for (id, mut todo) in todos {
+                if !selected.contains(&id) || tag.is_subset(&todo.tags) {
+                    continue;
+                }
+                todo.tags.extend(tag.iter().cloned());
+                todo.tags = validation::tags(todo.tags.iter().map(String::as_str))
+                    .map_err(ApplicationError::InvalidInput)?;
+                changed.push((id, todo));
+            }
+            state.todos.update_many(list.id, changed).await?;
+        }
+        BulkAction::Delete => {
+            deleted = state.todos.delete_many(list.id, &form.ids).await?;
+        }
+        BulkAction::Restore => {
+            // Restored todos not done reopen their ancestors, as adding them would
+            state.todos.restore_many(list.id, &form.ids).await?;
+        }
+        BulkAction::Move => {
+            let to_list = form.to_list.ok_or_else(|| {
+                ApplicationError::InvalidInput("Pick the list to move the todos to".to_owned())
+            })?;
+            let target = state
+                .lists
+                .get(user.id, to_list)
+                .await?
+                .ok_or(ApplicationError::NotFound)?;
+            target.require(Role::Editor)?;
+            if target.id != list.id {
+                let ids = outermost(&todos, &form.ids);
+                state.todos.move_to_list(list.id, &ids, target.id).await?;
+                state.events.publish(target.id, None, TodoChange::Reload);
+            }
+        }
+    }
+
+    state.events.publish(list.id, client, TodoChange::Reload);
+    render_todos(&state, &list, &form.filters, deleted).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{list_id}/todos/toggle-all",
+    params(("list_id" = i64, Path, description = "Id of the list")),
+    request_body(content = GetTodosForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "The matching todo rows, all of the list done, or all not done when they already were", content_type = "text/html"),
+        (status = 403, description = "Viewers cannot change the todos", content_type = "text/html"),
+        (status = 404, description = "No such list", content_type = "text/html"),
+        (status = 409, description = "One of the todos changed meanwhile, none was changed", content_type = "text/html")
+    )
+)]
+pub async fn toggle_all(
+    State(state): State<AppState>,
+    CurrentList(list): CurrentList,
+    ClientId(client): ClientId,
+    Form(filters): Form<GetTodosForm>,
+) -> Result<impl IntoResponse, ApplicationError> {
+    list.require(Role::Editor)?;
+    let todos = state.todos.list(list.id).await?;
+    let done = todos.iter().any(|(_, todo)| !todo.done);
+    let changed = todos
+        .into_iter()
+        .filter(|(_, todo)| todo.done != done)
+        .map(|(id, mut todo)| {
+            todo.done = done;
+            (id, todo)
+        })
+        .collect();
+    state.todos.update_many(list.id, changed).await?;
+    state.events.publish(list.id, client, TodoChange::Reload);
+    render_todos(&state, &list, &filters, Vec::new()).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{list_id}/todos/clear-completed",
+    params(("list_id" = i64, Path, description = "Id of the list")),
+    request_body(content = GetTodosForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "The matching todo rows, the done todos of the list moved to the trash, with a toast to undo it out of band", content_type = "text/html"),
+        (status = 403, description = "Viewers cannot change the todos", content_type = "text/html"),
+        (status = 404, description = "No such list", content_type = "text/html")
+    )
+)]
+pub async fn clear_completed(
+    State(state): State<AppState>,
+    CurrentList(list): CurrentList,
+    ClientId(client): ClientId,
+    Form(filters): Form<GetTodosForm>,
+) -> Result<impl IntoResponse, ApplicationError> {
+    list.require(Role::Editor)?;
+    let todos = state.todos.list(list.id).await?;
+    let done: Vec<i64> = todos
+        .iter()
+        .filter(|(_, todo)| todo.done)
+        .map(|(id, _)| *id)
+        .collect();
+    let deleted = state
+        .todos
+        .delete_many(list.id, &outermost(&todos, &done))
+        .await?;
+    state.events.publish(list.id, client, TodoChange::Reload);
+    render_todos(&state, &list, &filters, deleted).await
+}
+
+/// The first page of the todos, for the `#todos` of the list page, followed by the footer and the
+/// toast to undo the deletion of the todos `deleted`, cleared without any.
+async fn render_todos(
+    state: &AppState,
+    list: &TodoList,
+    filters: &GetTodosForm,
+    deleted: Vec<i64>,
+) -> Result<Html<String>, ApplicationError> {
+    let (todos, next) = filters.page(state.todos.list(list.id).await?, Utc::now(), None);
+    let list_id = list.id;
+    let readonly = list.role < Role::Editor;
+    let editors = state.presence.editors(list_id);
+    let terms = filters.terms();
+    let sortable = filters.sortable();
+    let counts = state.todos.counts(list_id).await?;
+    Ok(Html(
+        leptos::ssr::render_to_string(move || {
+            view! {
+                <TodoPage list_id todos next readonly sortable editors terms/>
+                <TodoFooter list_id counts oob=true/>
+                <UndoToast list_id ids=deleted oob=true/>
+            }
+        })
+        .into_owned(),
+    ))
+}
+
+/// The todos to update for those of `ids` to be `done`: completing a todo completes its
+/// subtasks, reopening it reopens its ancestors, as when changing a single todo.
+fn with_done(todos: Vec<(i64, Todo)>, ids: &[i64], done: bool) -> Vec<(i64, Todo)> {
+    let mut subtasks: HashMap<i64, Vec<i64>> = HashMap::new();
+    let mut parents = HashMap::new();
+    for (id, todo) in &todos {
+        if let Some(parent_id) = todo.parent_id {
+            subtasks.entry(parent_id).or_default().push(*id);
+            parents.insert(*id, parent_id);
+        }
+    }
+
+    let mut changed = HashSet::new();
+    let mut pending = ids.to_vec();
+    while let Some(id) = pending.pop() {
+        if !changed.insert(id) {
+            continue;
+        }
+        if done {
+            pending.extend(subtasks.get(&id).into_iter().flatten());
+        } else {
+            pending.extend(parents.get(&id));
+        }
+    }
+    todos
+        .into_iter()
+        .filter(|(id, todo)| changed.contains(id) && todo.done != done)
+        .map(|(id, mut todo)| {
+            todo.done = done;
+            (id, todo)
+        })
+        .collect()
+}
+
+/// The todos of `ids` which are not subtasks of another one of `ids`, which they go along with.
+fn outermost(todos: &[(i64, Todo)], ids: &[i64]) -> Vec<i64> {
+    let parents: HashMap<i64, i64> = todos
+        .iter()
+        .filter_map(|(id, todo)| Some((*id, todo.parent_id?)))
+        .collect();
+    let selected: HashSet<i64> = ids.iter().copied().collect();
+    ids.iter()
+        .copied()
+        .filter(|id| {
+            let mut ancestor = parents.get(id);
+            while let Some(id) = ancestor {
+                if selected.contains(id) {
+                    return false;
+                }
+                ancestor = parents.get(id);
+            }
+            true
+        })
+        .collect()
+}
+
+/// Actions on the todos selected with their checkbox, and on the whole list. `targets` are the
+/// other lists the todos can be moved to.
+#[component]
+pub fn BulkActions(list_id: i64, targets: Vec<TodoList>) -> impl IntoView {
+    view! {
+        <form
+            id="bulk"
+            class="flex flex-row gap-2 items-center"
+            hx-post=format!("/lists/{list_id}/todos/bulk")
+            hx-include="#filters"
+            hx-target="#todos"
+        >
+            <input type="hidden" name="ids" value=""/>
+            <span class="selected-count text-sm">0 selected</span>
+            <select name="action">
+                {BulkAction::ALL
+                    .into_iter()
+                    .map(|action| view! { <option value=action.value()>{action.label()}</option> })
+                    .collect_view()}
+            </select>
+            <input
+                type="text"
+                name="new_tag"
+                placeholder="Tag to add"
+                maxlength=validation::MAX_TAG_LENGTH
+            />
+            <select name="to_list">
+                <option value="">List to move to</option>
+                {targets
+                    .into_iter()
+                    .map(|target| view! { <option value=target.id>{target.name}</option> })
+                    .collect_view()}
+            </select>
+            <button class="bg-teal-200 rounded-md p-2" type="submit">
+                Apply to selected
+            </button>
+            <button type="button" hx-post=format!("/lists/{list_id}/todos/toggle-all")>
+                Toggle all
+            </button>
+            <button type="button" hx-post=format!("/lists/{list_id}/todos/clear-completed")>
+                Clear completed
+            </button>
+        </form>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{outermost, with_done};
+    use crate::todos::Todo;
+
+    /// 1 with the subtasks 2 and 3, 4 being a subtask of 3, and 5 on its own, `done` ones done.
+    fn tree(done: &[i64]) -> Vec<(i64, Todo)> {
+        [
+            (1, None),
+            (2, Some(1)),
+            (3, Some(1)),
+            (4, Some(3)),
+            (5, None),
+        ]
+        .into_iter()
+        .map(|(id, parent_id)| {
+            let todo = Todo {
+                parent_id,
+                done: done.contains(&id),
+                ..Todo::new(format!("Todo {id}"))
+            };
+            (id, todo)
+        })
+        .collect()
+    }
+
+    fn ids(todos: &[(i64, Todo)]) -> Vec<i64> {
+        let mut ids: Vec<i64> = todos.iter().map(|(id, _)| *id).collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    #[test]
+    fn completing_completes_the_subtasks() {
+        let changed = with_done(tree(&[2]), &[1], true);
+        assert_eq!(ids(&changed), [1, 3, 4]);
+        assert!(changed.iter().all(|(_, todo)| todo.done));
+
+        assert_eq!(ids(&with_done(tree(&[]), &[3, 5], true)), [3, 4, 5]);
+    }
+
+    #[test]
+    fn reopening_reopens_the_ancestors() {
+        let changed = with_done(tree(&[1, 2, 3, 4]), &[4], false);
+        assert_eq!(ids(&changed), [1, 3, 4]);
+        assert!(changed.iter().all(|(_, todo)| !todo.done));
+
+        // Todos already in the state asked for are left alone
+        assert!(with_done(tree(&[]), &[4], false).is_empty());
+    }
+
+    #[test]
+    fn outermost_leaves_out_the_subtasks_of_selected_todos() {
+        let todos = tree(&[]);
+        assert_eq!(outermost(&todos, &[4, 1, 5]), [1, 5]);
+        assert_eq!(outermost(&todos, &[4, 2]), [4, 2]);
+        assert_eq!(outermost(&todos, &[3, 4]), [3]);
+        assert_eq!(outermost(&todos, &[404]), [404]);
+    }
+}
@@ -0,0 +1,153 @@
+use leptos::*;
+
+/// Lets htmx swap error responses, whose target is set by the `HX-Retarget` header.
+const SWAP_ERRORS_SCRIPT: &str = r#"
+document.addEventListener("htmx:beforeSwap", (event) => {
+    if (event.detail.xhr.status >= 400) {
+        event.detail.shouldSwap = true;
+        event.detail.isError = false;
+    }
+});
+"#;
+
+/// Fills the empty time zone fields of the forms with the time zone of the browser.
+const TIME_ZONE_SCRIPT: &str = r#"
+htmx.onLoad((content) => {
+    content.querySelectorAll("input[name=time_zone]").forEach((input) => {
+        if (!input.value) {
+            input.value = Intl.DateTimeFormat().resolvedOptions().timeZone;
+        }
+    });
+});
+"#;
+
+/// Filters the list on the tag of the chip, or the status of the footer count, clicked.
+const FILTER_LINKS_SCRIPT: &str = r#"
+document.addEventListener("click", (event) => {
+    const link = event.target.closest("[data-tag], [data-filter]");
+    const filters = document.getElementById("filters");
+    if (!link || !filters) {
+        return;
+    }
+    if (link.dataset.tag) {
+        filters.elements.tag.value = link.dataset.tag;
+    } else {
+        filters.elements.filter.value = link.dataset.filter;
+    }
+    htmx.trigger(filters, "change");
+});
+"#;
+
+/// Makes the `.sortable` containers of todos reorderable by dragging their handle. Their
+/// `hx-trigger="end"` request gets the todo moved and the one it was dropped after, nothing when
+/// it was dropped where it started.
+const SORTABLE_SCRIPT: &str = r#"
+htmx.onLoad((content) => {
+    const containers = [...content.querySelectorAll(".sortable")];
+    if (content.matches(".sortable")) {
+        containers.push(content);
+    }
+    containers.forEach((container) => {
+        if (Sortable.get(container)) {
+            return;
+        }
+        new Sortable(container, {
+            handle: ".drag-handle",
+            draggable: ".todo-node",
+            onUpdate: (event) => {
+                const after = event.item.previousElementSibling;
+                container.setAttribute("hx-vals", JSON.stringify({
+                    id: event.item.dataset.id,
+                    after: after?.dataset.id,
+                }));
+            },
+            onEnd: () => container.removeAttribute("hx-vals"),
+        });
+    });
+});
+"#;
+
+/// Keeps the ids of the todos selected with their checkbox in the form of the bulk actions, the
+/// selection being cleared whenever the todos are rendered again.
+const BULK_SELECTION_SCRIPT: &str = r#"
+function updateSelection() {
+    const bulk = document.getElementById("bulk");
+    if (!bulk) {
+        return;
+    }
+    const ids = [...document.querySelectorAll(".select-todo:checked")].map((input) => input.value);
+    bulk.elements.ids.value = ids.join(",");
+    bulk.querySelector(".selected-count").textContent = `${ids.length} selected`;
+}
+document.addEventListener("change", (event) => {
+    if (event.target.matches(".select-todo")) {
+        updateSelection();
+    }
+});
+htmx.onLoad(updateSelection);
+"#;
+
+/// Drops the todos already shown from the pages loaded by scrolling the list: the ones added live
+/// after the list page was loaded.
+const MORE_TODOS_SCRIPT: &str = r#"
+document.addEventListener("htmx:beforeSwap", (event) => {
+    if (event.detail.target.id !== "more-todos") {
+        return;
+    }
+    const page = document.createElement("template");
+    page.innerHTML = event.detail.serverResponse;
+    page.content.querySelectorAll(".todo-node").forEach((node) => {
+        if (document.getElementById(node.id)) {
+            node.remove();
+        }
+    });
+    event.detail.serverResponse = page.innerHTML;
+});
+"#;
+
+/// Full page skeleton, with the `#errors` region error fragments are swapped into.
+#[component]
+pub fn Page(children: Children) -> impl IntoView {
+    view! {
+        <head>
+            <script src="https://unpkg.com/htmx.org@1.9.10"></script>
+            <script src="https://unpkg.com/htmx.org@1.9.10/dist/ext/sse.js"></script>
+            <script src="https://unpkg.com/htmx.org@1.9.10/dist/ext/ws.js"></script>
+            <script src="https://cdn.tailwindcss.com"></script>
+            <script src="https://cdn.jsdelivr.net/npm/sortablejs@1.15.2/Sortable.min.js"></script>
+            <script inner_html=SWAP_ERRORS_SCRIPT></script>
+            <script inner_html=TIME_ZONE_SCRIPT></script>
+            <script inner_html=FILTER_LINKS_SCRIPT></script>
+            <script inner_html=SORTABLE_SCRIPT></script>
+            <script inner_html=MORE_TODOS_SCRIPT></script>
+            <script inner_html=BULK_SELECTION_SCRIPT></script>
+        </head>
+        <body class="w-1/2 m-auto">
+            <div id="errors" class="fixed top-4 right-4"></div>
+            {children()}
+        </body>
+    }
+}
+
+#[component]
+pub fn ErrorAlert(message: String) -> impl IntoView {
+    view! {
+        <div role="alert" class="flex flex-row gap-4 bg-red-100 text-red-800 rounded-md p-4">
+            <p>{message}</p>
+            <button onclick="this.parentElement.remove()">Dismiss</button>
+        </div>
+    }
+}
+
+#[component]
+pub fn ErrorPage(message: String) -> impl IntoView {
+    view! {
+        <Page>
+            <h1 class="text-3xl">TodoMVC</h1>
+            <p class="text-xl">{message}</p>
+            <a class="underline" href="/">
+                Back to the list
+            </a>
+        </Page>
+    }
+}
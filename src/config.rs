@@ -0,0 +1,70 @@
+use std::{env, num::NonZeroU64, str::FromStr};
+
+use anyhow::Context;
+
+/// Runtime settings, read from the environment at startup.
+pub struct Config {
+    /// Address the HTTP server listens on (`BIND_ADDR`).
+    pub bind_addr: String,
+    /// SQLite database holding the todos and the sessions (`DATABASE_URL`). Use `sqlite::memory:`
+    /// to keep everything in memory.
+    pub database_url: String,
+    /// Seconds of inactivity after which a session expires (`SESSION_EXPIRY_SECS`).
+    pub session_expiry_secs: i64,
+    /// Seconds between two purges of the expired sessions (`SESSION_CLEANUP_INTERVAL_SECS`).
+    pub session_cleanup_interval_secs: u64,
+    /// Only send the session cookie over HTTPS (`SESSION_SECURE`).
+    pub session_secure: bool,
+    /// URL the users reach the server at, used in the invite links (`PUBLIC_URL`).
+    pub public_url: String,
+    /// Seconds an invite link to a list stays valid (`INVITE_EXPIRY_SECS`).
+    pub invite_expiry_secs: i64,
+    /// Seconds between two checks for the todos coming due (`REMINDER_INTERVAL_SECS`).
+    pub reminder_interval_secs: NonZeroU64,
+    /// URL the reminders are posted to, they are only logged without it (`REMINDER_WEBHOOK_URL`).
+    pub reminder_webhook_url: Option<String>,
+    /// Seconds the deleted todos stay in the trash before being purged (`TRASH_RETENTION_SECS`).
+    pub trash_retention_secs: u64,
+    /// Seconds between two purges of the trash (`TRASH_PURGE_INTERVAL_SECS`).
+    pub trash_purge_interval_secs: NonZeroU64,
+}
+
+impl Config {
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            bind_addr: env::var("BIND_ADDR").unwrap_or_else(|_| "0.0.0.0:3000".into()),
+            database_url: env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite://todos.db".into()),
+            session_expiry_secs: parse_var("SESSION_EXPIRY_SECS", 30 * 24 * 60 * 60)?,
+            session_cleanup_interval_secs: parse_var("SESSION_CLEANUP_INTERVAL_SECS", 60)?,
+            session_secure: parse_var("SESSION_SECURE", false)?,
+            public_url: env::var("PUBLIC_URL")
+                .unwrap_or_else(|_| "http://localhost:3000".into())
+                .trim_end_matches('/')
+                .to_owned(),
+            invite_expiry_secs: parse_var("INVITE_EXPIRY_SECS", 7 * 24 * 60 * 60)?,
+            reminder_interval_secs: parse_var(
+                "REMINDER_INTERVAL_SECS",
+                NonZeroU64::new(60).unwrap(),
+            )?,
+            reminder_webhook_url: env::var("REMINDER_WEBHOOK_URL").ok(),
+            trash_retention_secs: parse_var("TRASH_RETENTION_SECS", 30 * 24 * 60 * 60)?,
+            trash_purge_interval_secs: parse_var(
+                "TRASH_PURGE_INTERVAL_SECS",
+                NonZeroU64::new(60 * 60).unwrap(),
+            )?,
+        })
+    }
+}
+
+fn parse_var<T>(key: &str, default: T) -> anyhow::Result<T>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    match env::var(key) {
+        Ok(value) => value
+            .parse()
+            .with_context(|| format!("invalid value for {key}")),
+        Err(_) => Ok(default),
+    }
+}
@@ -1,22 +1,157 @@
-use axum::{http::StatusCode, response::IntoResponse};
+use axum::{
+    extract::{
+        rejection::{FormRejection, JsonRejection, PathRejection, QueryRejection},
+        Request,
+    },
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{Html, IntoResponse, Redirect, Response},
+    Json,
+};
+use leptos::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    components::{ErrorAlert, ErrorPage},
+    repository::StaleVersion,
+};
 
 pub enum ApplicationError {
     NotFound,
+    BadRequest(String),
+    Conflict(String),
+    InvalidInput(String),
+    Unauthorized,
+    Forbidden,
+    SessionUnavailable,
     InternalError(String),
 }
 
+/// Body of the API error responses.
+#[derive(Serialize, ToSchema)]
+pub struct ErrorBody {
+    error: String,
+}
+
+/// Message shown to the user, left on the response for [`render_errors`] to pick up.
+#[derive(Clone)]
+struct ErrorMessage(String);
+
 impl IntoResponse for ApplicationError {
     fn into_response(self) -> axum::response::Response {
-        match self {
+        let (status, message) = match self {
             ApplicationError::NotFound => (StatusCode::NOT_FOUND, "Not found".to_owned()),
-            ApplicationError::InternalError(e) => (StatusCode::INTERNAL_SERVER_ERROR, e),
+            ApplicationError::BadRequest(e) => (StatusCode::BAD_REQUEST, e),
+            ApplicationError::Conflict(e) => (StatusCode::CONFLICT, e),
+            ApplicationError::InvalidInput(e) => (StatusCode::UNPROCESSABLE_ENTITY, e),
+            ApplicationError::Unauthorized => {
+                (StatusCode::UNAUTHORIZED, "You need to log in".to_owned())
+            }
+            ApplicationError::Forbidden => (
+                StatusCode::FORBIDDEN,
+                "You are not allowed to do this".to_owned(),
+            ),
+            ApplicationError::SessionUnavailable => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Your session is unavailable, please try again later".to_owned(),
+            ),
+            ApplicationError::InternalError(e) => {
+                tracing::error!("internal error: {e}");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Something went wrong".to_owned(),
+                )
+            }
+        };
+        let mut response = (status, message.clone()).into_response();
+        response.extensions_mut().insert(ErrorMessage(message));
+        response
+    }
+}
+
+/// Renders the errors returned by the handlers.
+///
+/// API requests get a JSON body, htmx requests get an alert fragment retargeted to the `#errors`
+/// region of the page, other requests get a full error page. Pages needing a logged in user
+/// redirect to the login page instead, which sends the user back to the page once logged in.
+pub async fn render_errors(request: Request, next: Next) -> Response {
+    let api = request.uri().path().starts_with("/api/");
+    let htmx = request.headers().contains_key("HX-Request");
+    let login = if request.method() == Method::GET {
+        format!("/login?next={}", request.uri().path())
+    } else {
+        "/login".to_owned()
+    };
+    let mut response = next.run(request).await;
+    let Some(ErrorMessage(message)) = response.extensions_mut().remove() else {
+        return response;
+    };
+
+    let status = response.status();
+    if api {
+        (status, Json(ErrorBody { error: message })).into_response()
+    } else if status == StatusCode::UNAUTHORIZED {
+        if htmx {
+            [("HX-Redirect", "/login")].into_response()
+        } else {
+            Redirect::to(&login).into_response()
         }
-        .into_response()
+    } else if htmx {
+        let html =
+            leptos::ssr::render_to_string(move || view! { <ErrorAlert message/> }).into_owned();
+        (
+            status,
+            [("HX-Retarget", "#errors"), ("HX-Reswap", "innerHTML")],
+            Html(html),
+        )
+            .into_response()
+    } else {
+        let html =
+            leptos::ssr::render_to_string(move || view! { <ErrorPage message/> }).into_owned();
+        (status, Html(html)).into_response()
     }
 }
 
 impl From<anyhow::Error> for ApplicationError {
     fn from(value: anyhow::Error) -> Self {
-        Self::InternalError(value.to_string())
+        if value.is::<StaleVersion>() {
+            return Self::Conflict(
+                "This todo was changed in the meantime, reload the page to see its latest version"
+                    .to_owned(),
+            );
+        }
+        Self::InternalError(format!("{value:#}"))
+    }
+}
+
+impl From<tower_sessions::session::Error> for ApplicationError {
+    fn from(value: tower_sessions::session::Error) -> Self {
+        tracing::error!("session error: {value}");
+        Self::SessionUnavailable
+    }
+}
+
+impl From<FormRejection> for ApplicationError {
+    fn from(value: FormRejection) -> Self {
+        Self::BadRequest(value.body_text())
+    }
+}
+
+impl From<PathRejection> for ApplicationError {
+    fn from(value: PathRejection) -> Self {
+        Self::BadRequest(value.body_text())
+    }
+}
+
+impl From<QueryRejection> for ApplicationError {
+    fn from(value: QueryRejection) -> Self {
+        Self::BadRequest(value.body_text())
+    }
+}
+
+impl From<JsonRejection> for ApplicationError {
+    fn from(value: JsonRejection) -> Self {
+        Self::BadRequest(value.body_text())
     }
 }
@@ -0,0 +1,171 @@
+//! Live updates of the list pages: every change to the todos of a list is broadcast to the pages
+//! showing it, through Server-Sent Events carrying out-of-band fragments for the htmx SSE
+//! extension.
+
+use std::{collections::HashMap, convert::Infallible, sync::Mutex};
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, State},
+    http::request::Parts,
+    response::{
+        sse::{Event, KeepAlive},
+        IntoResponse, Sse,
+    },
+};
+use leptos::*;
+use serde::Deserialize;
+use tokio::sync::broadcast;
+use tokio_stream::{wrappers::BroadcastStream, StreamExt};
+use utoipa::IntoParams;
+
+use crate::{
+    auth::AuthUser,
+    errors::ApplicationError,
+    extract::Query,
+    lists::CurrentList,
+    members::Role,
+    todos::{ReloadTodos, Todo, TodoNode},
+    AppState,
+};
+
+/// Events a subscriber can fall behind by before missing some.
+const CHANNEL_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone)]
+pub enum TodoChange {
+    Created(i64, Todo),
+    Updated(i64, Todo),
+    Deleted(i64),
+    /// Todos were moved, restored from the trash or changed in bulk, the pages reload their todos
+    /// to show them in place.
+    Reload,
+}
+
+#[derive(Debug, Clone)]
+pub struct ListEvent {
+    /// [`ClientId`] of the page that made the change, which already shows it.
+    origin: Option<String>,
+    change: TodoChange,
+}
+
+/// One broadcast channel per list, created when a first page subscribes to it.
+#[derive(Default)]
+pub struct ListEvents {
+    channels: Mutex<HashMap<i64, broadcast::Sender<ListEvent>>>,
+}
+
+impl ListEvents {
+    pub fn subscribe(&self, list_id: i64) -> broadcast::Receiver<ListEvent> {
+        let mut channels = self.channels.lock().unwrap();
+        channels
+            .entry(list_id)
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+            .subscribe()
+    }
+
+    pub fn publish(&self, list_id: i64, origin: Option<String>, change: TodoChange) {
+        let mut channels = self.channels.lock().unwrap();
+        let Some(sender) = channels.get(&list_id) else {
+            return;
+        };
+        // Sending only fails once every subscriber is gone
+        if sender.send(ListEvent { origin, change }).is_err() {
+            channels.remove(&list_id);
+        }
+    }
+}
+
+/// Id a list page picks for itself and htmx sends back in the `X-Client-Id` header, so the page
+/// is not sent the changes it made.
+pub struct ClientId(pub Option<String>);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ClientId {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        let id = parts
+            .headers
+            .get("X-Client-Id")
+            .and_then(|value| value.to_str().ok())
+            .map(ToOwned::to_owned);
+        Ok(ClientId(id))
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EventsQuery {
+    /// [`ClientId`] of the subscribing page.
+    client: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/lists/{list_id}/events",
+    params(("list_id" = i64, Path, description = "Id of the list"), EventsQuery),
+    responses(
+        (status = 200, description = "The changes to the todos of the list, as `todo` events holding out-of-band fragments", content_type = "text/event-stream"),
+        (status = 404, description = "No such list", content_type = "text/html")
+    )
+)]
+pub async fn list_events(
+    State(state): State<AppState>,
+    user: AuthUser,
+    CurrentList(list): CurrentList,
+    Query(query): Query<EventsQuery>,
+) -> Result<impl IntoResponse, ApplicationError> {
+    let list_id = list.id;
+    let stream = BroadcastStream::new(state.events.subscribe(list_id))
+        // Subscribers lagging behind skip the events they missed
+        .filter_map(Result::ok)
+        .filter(move |event| event.origin.is_none() || event.origin != query.client)
+        // The role of the user may have changed since the page subscribed, the events follow it
+        // and end with the membership
+        .then(move |event| {
+            let state = state.clone();
+            async move {
+                let list = state.lists.get(user.id, list_id).await.ok().flatten()?;
+                let readonly = list.role < Role::Editor;
+                Some(render_change(&state, list_id, event.change, readonly))
+            }
+        })
+        .map_while(|html| {
+            Some(Ok::<_, Infallible>(
+                Event::default().event("todo").data(html?),
+            ))
+        });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+fn render_change(state: &AppState, list_id: i64, change: TodoChange, readonly: bool) -> String {
+    let editor = match change {
+        TodoChange::Updated(id, _) => state.presence.editor(list_id, id),
+        _ => None,
+    };
+    leptos::ssr::render_to_string(move || match change {
+        TodoChange::Created(id, todo) => {
+            let target = todo.parent_id.map_or_else(
+                || "#todos".to_owned(),
+                |parent_id| format!("#subtasks-{parent_id}"),
+            );
+            // The filters of each page are not known here, the subtasks of the todo can be
+            // dragged once the todos are loaded again
+            view! {
+                <div hx-swap-oob=format!("beforeend:{target}")>
+                    <TodoNode list_id id todo readonly/>
+                </div>
+            }
+            .into_view()
+        }
+        TodoChange::Updated(id, todo) => {
+            view! { <Todo list_id id todo readonly editor oob=true/> }.into_view()
+        }
+        TodoChange::Deleted(id) => {
+            view! { <div id=format!("node-{id}") hx-swap-oob="delete"></div> }.into_view()
+        }
+        TodoChange::Reload => view! { <ReloadTodos list_id/> }.into_view(),
+    })
+    .into_owned()
+}
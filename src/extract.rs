@@ -0,0 +1,23 @@
+use axum::extract::{FromRequest, FromRequestParts};
+
+use crate::errors::ApplicationError;
+
+/// `axum::Form` rejecting with an [`ApplicationError`].
+#[derive(FromRequest)]
+#[from_request(via(axum::Form), rejection(ApplicationError))]
+pub struct Form<T>(pub T);
+
+/// `axum::extract::Path` rejecting with an [`ApplicationError`].
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Path), rejection(ApplicationError))]
+pub struct Path<T>(pub T);
+
+/// `axum::extract::Query` rejecting with an [`ApplicationError`].
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(ApplicationError))]
+pub struct Query<T>(pub T);
+
+/// `axum::Json` rejecting with an [`ApplicationError`].
+#[derive(FromRequest)]
+#[from_request(via(axum::Json), rejection(ApplicationError))]
+pub struct Json<T>(pub T);
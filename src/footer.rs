@@ -0,0 +1,81 @@
+//! Footer of the list page, counting its todos. The responses changing the todos swap it out of
+//! band, and the pages reload it when they receive the changes made by the others.
+
+use axum::{
+    extract::State,
+    response::{Html, IntoResponse},
+};
+use leptos::*;
+
+use crate::{errors::ApplicationError, lists::CurrentList, todos::Filter, AppState};
+
+/// Todos of a list, subtasks included, those in the trash left out.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TodoCounts {
+    pub total: i64,
+    pub done: i64,
+}
+
+impl TodoCounts {
+    /// Todos matching the filter.
+    fn matching(self, filter: Filter) -> i64 {
+        match filter {
+            Filter::All => self.total,
+            Filter::Done => self.done,
+            Filter::NotDone => self.total - self.done,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/lists/{list_id}/footer",
+    params(("list_id" = i64, Path, description = "Id of the list")),
+    responses(
+        (status = 200, description = "The footer of the list page, counting its todos", content_type = "text/html"),
+        (status = 404, description = "No such list", content_type = "text/html")
+    )
+)]
+pub async fn get_footer(
+    State(state): State<AppState>,
+    CurrentList(list): CurrentList,
+) -> Result<impl IntoResponse, ApplicationError> {
+    let counts = state.todos.counts(list.id).await?;
+    Ok(Html(
+        leptos::ssr::render_to_string(move || view! { <TodoFooter list_id=list.id counts/> })
+            .into_owned(),
+    ))
+}
+
+/// The todos left and done, and how many each status filter shows, clicking one applies it.
+/// `oob` swaps the footer out of band, in place of the current one.
+#[component]
+pub fn TodoFooter(list_id: i64, counts: TodoCounts, #[prop(optional)] oob: bool) -> impl IntoView {
+    let left = counts.matching(Filter::NotDone);
+    view! {
+        // The changes made by the other pages come as server-sent events
+        <footer
+            id="footer"
+            class="flex flex-row justify-between items-center text-sm"
+            hx-swap-oob=oob.then_some("true")
+            hx-get=format!("/lists/{list_id}/footer")
+            hx-trigger="htmx:sseMessage from:body delay:200ms"
+            hx-swap="outerHTML"
+        >
+            <span>{if left == 1 { "1 item left".to_owned() } else { format!("{left} items left") }}</span>
+            <span>{format!("{} completed", counts.done)}</span>
+            <div class="flex flex-row gap-2">
+                {Filter::ALL
+                    .into_iter()
+                    .map(|filter| {
+                        view! {
+                            <button type="button" class="underline" data-filter=filter.value()>
+                                {format!("{} ({})", filter.label(), counts.matching(filter))}
+                            </button>
+                        }
+                    })
+                    .collect_view()}
+            </div>
+        </footer>
+    }
+}
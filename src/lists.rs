@@ -0,0 +1,380 @@
+//! Todo lists: the page showing a list with the sidebar of all the lists of the user, and the
+//! [`CurrentList`] extractor scoping the todo routes to the lists the user is a member of.
+
+use std::collections::HashMap;
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, State},
+    http::request::Parts,
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use chrono::Utc;
+use leptos::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    auth::AuthUser,
+    bulk::BulkActions,
+    components::Page,
+    errors::ApplicationError,
+    extract::{Form, Path, Query},
+    footer::{TodoCounts, TodoFooter},
+    members::{Member, MembersPanel, Role},
+    presence::OnlineUsers,
+    todos::{Cursor, GetTodosForm, NewTodoForm, Todo, TodoFilters, TodoPage},
+    trash::UndoToast,
+    validation, AppState,
+};
+
+/// Name of the list created for users who have none.
+const DEFAULT_LIST_NAME: &str = "Todo";
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+pub struct TodoList {
+    pub id: i64,
+    pub name: String,
+    /// Role of the user the list was loaded for.
+    pub role: Role,
+}
+
+impl TodoList {
+    /// Rejects with [`ApplicationError::Forbidden`] unless the user has at least `role`.
+    pub fn require(&self, role: Role) -> Result<(), ApplicationError> {
+        if self.role >= role {
+            Ok(())
+        } else {
+            Err(ApplicationError::Forbidden)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ListPath {
+    list_id: i64,
+}
+
+/// List of the `:list_id` route parameter, which the logged in user has access to.
+pub struct CurrentList(pub TodoList);
+
+#[async_trait]
+impl FromRequestParts<AppState> for CurrentList {
+    type Rejection = ApplicationError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let user = AuthUser::from_request_parts(parts, state).await?;
+        let Path(ListPath { list_id }) = Path::from_request_parts(parts, state).await?;
+        let list = state
+            .lists
+            .get(user.id, list_id)
+            .await?
+            .ok_or(ApplicationError::NotFound)?;
+        Ok(CurrentList(list))
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/",
+    responses((status = 303, description = "Redirects to the first list of the user"))
+)]
+pub async fn root(
+    State(state): State<AppState>,
+    user: AuthUser,
+) -> Result<impl IntoResponse, ApplicationError> {
+    let list = first_list(&state, user.id).await?;
+    Ok(Redirect::to(&format!("/lists/{}", list.id)))
+}
+
+/// The first list of the user, created when they have none.
+pub async fn first_list(state: &AppState, user_id: i64) -> anyhow::Result<TodoList> {
+    match state.lists.list(user_id).await?.into_iter().next() {
+        Some(list) => Ok(list),
+        None => state.lists.create(user_id, DEFAULT_LIST_NAME).await,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/lists/{list_id}",
+    params(("list_id" = i64, Path, description = "Id of the list"), GetTodosForm),
+    responses(
+        (status = 200, description = "The list page, with the matching todos", content_type = "text/html"),
+        (status = 404, description = "No such list", content_type = "text/html")
+    )
+)]
+pub async fn list_page(
+    State(state): State<AppState>,
+    user: AuthUser,
+    CurrentList(list): CurrentList,
+    Query(filters): Query<GetTodosForm>,
+) -> Result<impl IntoResponse, ApplicationError> {
+    let lists = state.lists.list(user.id).await?;
+    let (todos, next) = filters.page(state.todos.list(list.id).await?, Utc::now(), None);
+    let counts = state.todos.counts(list.id).await?;
+    let members = state.members.list(list.id).await?;
+    let editors = state.presence.editors(list.id);
+    Ok(Html(
+        leptos::ssr::render_to_string(move || {
+            view! { <ListPage user lists list filters todos next counts members editors/> }
+        })
+        .into_owned(),
+    ))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ListForm {
+    pub name: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists",
+    request_body(content = ListForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Redirects to the new list with `HX-Redirect`"),
+        (status = 422, description = "The form with its errors", content_type = "text/html")
+    )
+)]
+pub async fn create_list(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Form(form): Form<ListForm>,
+) -> Result<Response, ApplicationError> {
+    let name = match validation::list_name(&form.name) {
+        Ok(name) => name,
+        Err(error) => {
+            let form = leptos::ssr::render_to_string(move || {
+                view! { <NewListForm value=form.name error/> }
+            });
+            return Ok(validation::invalid_form("#new-list", form.into_owned()));
+        }
+    };
+
+    let list = state.lists.create(user.id, &name).await?;
+    Ok([("HX-Redirect", format!("/lists/{}", list.id))].into_response())
+}
+
+#[utoipa::path(
+    put,
+    path = "/lists/{list_id}",
+    params(("list_id" = i64, Path, description = "Id of the list")),
+    request_body(content = ListForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Reloads the page with `HX-Refresh`"),
+        (status = 403, description = "Only owners rename the list", content_type = "text/html"),
+        (status = 404, description = "No such list", content_type = "text/html"),
+        (status = 422, description = "The form with its errors", content_type = "text/html")
+    )
+)]
+pub async fn rename_list(
+    State(state): State<AppState>,
+    user: AuthUser,
+    CurrentList(mut list): CurrentList,
+    Form(form): Form<ListForm>,
+) -> Result<Response, ApplicationError> {
+    list.require(Role::Owner)?;
+    let name = match validation::list_name(&form.name) {
+        Ok(name) => name,
+        Err(error) => {
+            list.name = form.name;
+            let form = leptos::ssr::render_to_string(move || {
+                view! { <RenameListForm list error/> }
+            });
+            return Ok(validation::invalid_form("#rename-list", form.into_owned()));
+        }
+    };
+
+    state
+        .lists
+        .rename(user.id, list.id, &name)
+        .await?
+        .ok_or(ApplicationError::NotFound)?;
+    Ok([("HX-Refresh", "true")].into_response())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/lists/{list_id}",
+    params(("list_id" = i64, Path, description = "Id of the list")),
+    responses(
+        (status = 200, description = "Redirects to the first list with `HX-Redirect`"),
+        (status = 403, description = "Only owners delete the list", content_type = "text/html"),
+        (status = 404, description = "No such list", content_type = "text/html")
+    )
+)]
+pub async fn delete_list(
+    State(state): State<AppState>,
+    user: AuthUser,
+    CurrentList(list): CurrentList,
+) -> Result<impl IntoResponse, ApplicationError> {
+    list.require(Role::Owner)?;
+    state
+        .lists
+        .delete(user.id, list.id)
+        .await?
+        .ok_or(ApplicationError::NotFound)?;
+    Ok([("HX-Redirect", "/")])
+}
+
+#[component]
+fn ListPage(
+    user: AuthUser,
+    lists: Vec<TodoList>,
+    list: TodoList,
+    filters: GetTodosForm,
+    todos: Vec<(i64, Todo)>,
+    next: Option<Cursor>,
+    counts: TodoCounts,
+    members: Vec<Member>,
+    editors: HashMap<i64, String>,
+) -> impl IntoView {
+    let list_id = list.id;
+    let readonly = list.role < Role::Editor;
+    let user_id = user.id;
+    let terms = filters.terms();
+    let sortable = filters.sortable() && !readonly;
+    let targets: Vec<TodoList> = lists
+        .iter()
+        .filter(|other| other.id != list_id && other.role >= Role::Editor)
+        .cloned()
+        .collect();
+    // Tells the changes made by this page apart from those it is sent by the other pages
+    let client = Uuid::new_v4().simple().to_string();
+    view! {
+        <Page>
+            <div class="flex flex-row justify-between items-center">
+                <h1 class="text-3xl">TodoMVC</h1>
+                <form class="flex flex-row gap-2 items-center" method="post" action="/logout">
+                    <span>{user.username}</span>
+                    <button class="underline" type="submit">
+                        Log out
+                    </button>
+                </form>
+            </div>
+            <div class="flex flex-row gap-8">
+                <nav class="w-1/4 flex flex-col gap-2">
+                    <h2 class="text-xl">Lists</h2>
+                    {lists
+                        .into_iter()
+                        .map(|other| {
+                            view! {
+                                <a
+                                    class:font-bold=other.id == list_id
+                                    href=format!("/lists/{}", other.id)
+                                >
+                                    {other.name}
+                                </a>
+                            }
+                        })
+                        .collect_view()}
+                    <NewListForm/>
+                </nav>
+                <main
+                    class="w-3/4 flex flex-col"
+                    hx-headers=format!(r#"{{"X-Client-Id": "{client}"}}"#)
+                >
+                    <div
+                        hx-ext="sse"
+                        sse-connect=format!("/lists/{list_id}/events?client={client}")
+                        sse-swap="todo"
+                        hx-swap="none"
+                    ></div>
+                    <div
+                        hx-ext="ws"
+                        ws-connect=format!("/lists/{list_id}/presence?client={client}")
+                    ></div>
+                    <OnlineUsers/>
+                    {if list.role == Role::Owner {
+                        view! { <RenameListForm list=list.clone()/> }.into_view()
+                    } else {
+                        view! { <h2 class="text-2xl">{list.name.clone()}</h2> }.into_view()
+                    }}
+                    <TodoFilters list_id form=filters/>
+                    {(!readonly).then(|| view! { <BulkActions list_id targets/> })}
+                    // Editors reorder the todos by dragging them, see `reorder_todos`
+                    <div
+                        id="todos"
+                        class="flex flex-col"
+                        class:sortable=sortable
+                        hx-post=sortable.then(|| format!("/lists/{list_id}/todos/reorder"))
+                        hx-trigger=sortable.then_some("end consume")
+                    >
+                        <TodoPage list_id todos next readonly sortable editors terms/>
+                    </div>
+                    <div id="reload-todos"></div>
+                    <TodoFooter list_id counts/>
+                    <hr class="w-full"/>
+                    {(!readonly).then(|| view! { <NewTodoForm list_id/> })}
+                    <button
+                        class="underline self-start"
+                        hx-get=format!("/lists/{list_id}/trash")
+                        hx-target="#trash"
+                        hx-swap="outerHTML"
+                    >
+                        Trash
+                    </button>
+                    <div id="trash"></div>
+                    <UndoToast list_id/>
+                    <MembersPanel list user_id members/>
+                </main>
+            </div>
+        </Page>
+    }
+}
+
+#[component]
+fn NewListForm(
+    #[prop(optional)] value: String,
+    #[prop(optional)] error: Option<String>,
+) -> impl IntoView {
+    view! {
+        <form id="new-list" class="flex flex-col gap-2" hx-post="/lists">
+            <input
+                type="text"
+                name="name"
+                placeholder="New list"
+                value=value
+                required
+                maxlength=validation::MAX_LIST_NAME_LENGTH
+                aria-invalid=error.is_some().then_some("true")
+            />
+            {error.map(|error| view! { <p class="text-red-700">{error}</p> })}
+        </form>
+    }
+}
+
+#[component]
+fn RenameListForm(list: TodoList, #[prop(optional)] error: Option<String>) -> impl IntoView {
+    view! {
+        <form
+            id="rename-list"
+            class="flex flex-row gap-2 items-center"
+            hx-put=format!("/lists/{}", list.id)
+        >
+            <input
+                class="text-2xl"
+                type="text"
+                name="name"
+                value=list.name
+                required
+                maxlength=validation::MAX_LIST_NAME_LENGTH
+                aria-invalid=error.is_some().then_some("true")
+            />
+            <button type="submit">Rename</button>
+            <button
+                type="button"
+                hx-delete=format!("/lists/{}", list.id)
+                hx-confirm="Delete this list and all its todos?"
+            >
+                Delete
+            </button>
+            {error.map(|error| view! { <p class="text-red-700">{error}</p> })}
+        </form>
+    }
+}
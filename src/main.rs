@@ -1,204 +1,261 @@
+mod api;
+mod auth;
+mod bulk;
+mod components;
+mod config;
 mod errors;
+mod events;
+mod extract;
+mod footer;
+mod lists;
+mod members;
+mod openapi;
+mod presence;
+mod reminders;
+mod repository;
+mod search;
+mod todos;
+mod trash;
+mod validation;
 
-use std::collections::BTreeMap;
+use std::{sync::Arc, time::Duration};
 
 use axum::{
-    extract::Path,
-    response::{Html, IntoResponse},
-    routing::{delete, get, post, put},
-    Form, Router,
+    handler::Handler,
+    http::Method,
+    middleware,
+    routing::{on, MethodFilter, MethodRouter},
+    Router,
 };
+use config::Config;
 use errors::ApplicationError;
-use leptos::*;
-use serde::{Deserialize, Serialize};
+use events::ListEvents;
+use presence::Presence;
+use repository::{
+    ListRepository, MemberRepository, SqliteListRepository, SqliteMemberRepository,
+    SqliteTodoRepository, SqliteUserRepository, TodoRepository, UserRepository,
+};
 use tower_http::trace::TraceLayer;
-use tower_sessions::{MemoryStore, Session, SessionManagerLayer};
+use tower_sessions::{cookie::time, session_store::ExpiredDeletion, Expiry, SessionManagerLayer};
+use tower_sessions_sqlx_store::SqliteStore;
 use tracing_subscriber::prelude::*;
 
-const TODOS_KEY: &str = "todos";
-const INDEX_KEY: &str = "index";
+#[derive(Clone)]
+struct AppState {
+    config: Arc<Config>,
+    todos: Arc<dyn TodoRepository>,
+    lists: Arc<dyn ListRepository>,
+    members: Arc<dyn MemberRepository>,
+    users: Arc<dyn UserRepository>,
+    events: Arc<ListEvents>,
+    presence: Arc<Presence>,
+}
 
 #[tokio::main]
 async fn main() {
     tracing_subscriber::registry()
         .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "tower_http=debug,axum::rejection=trace".into()),
+            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+                "leptos_htmx=info,tower_http=debug,axum::rejection=trace".into()
+            }),
         )
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    let session_store = MemoryStore::default();
-    let session_layer = SessionManagerLayer::new(session_store).with_secure(false);
+    let config = Config::from_env().unwrap();
+    let pool = repository::connect(&config.database_url).await.unwrap();
 
-    let app = Router::new()
-        .route("/", get(root))
-        .route("/todos", get(get_todos))
-        .route("/todos", post(create_todo))
-        .route("/todos/:id", put(put_todo))
-        .route("/todos/:id", delete(delete_todo))
-        .layer(TraceLayer::new_for_http())
-        .layer(session_layer);
+    let todos: Arc<dyn TodoRepository> = Arc::new(SqliteTodoRepository::new(pool.clone()));
+    let session_store = SqliteStore::new(pool.clone());
+    session_store.migrate().await.unwrap();
+    tokio::spawn(
+        session_store
+            .clone()
+            .continuously_delete_expired(Duration::from_secs(config.session_cleanup_interval_secs)),
+    );
+    tokio::spawn(auth::drop_expired_todos(
+        todos.clone(),
+        session_store.clone(),
+        pool.clone(),
+        Duration::from_secs(config.session_cleanup_interval_secs),
+    ));
+    let session_layer = SessionManagerLayer::new(session_store)
+        .with_secure(config.session_secure)
+        .with_expiry(Expiry::OnInactivity(time::Duration::seconds(
+            config.session_expiry_secs,
+        )));
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
-    axum::serve(listener, app).await.unwrap();
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct Todo {
-    content: String,
-    done: bool,
-}
-
-async fn root(session: Session) -> impl IntoResponse {
-    if let None = session.get::<usize>(INDEX_KEY).await.unwrap() {
-        let mut map = BTreeMap::new();
-        map.insert(
-            0,
-            Todo {
-                content: "A faire".into(),
-                done: false,
-            },
-        );
-        session.insert(TODOS_KEY, map).await.unwrap();
-        session.insert(INDEX_KEY, 0).await.unwrap();
-    }
-    let todos: BTreeMap<usize, Todo> = session.get(TODOS_KEY).await.unwrap().unwrap();
-    Html(
-        leptos::ssr::render_to_string(|| {
-            view! {
-                <head>
-                    <script src="https://unpkg.com/htmx.org@1.9.10"></script>
-                    <script src="https://cdn.tailwindcss.com"></script>
-                </head>
-                <body class="w-1/2 m-auto">
-                    <h1 class="text-3xl">TodoMVC</h1>
-                    <select name="sort" hx-trigger="change" hx-get="/todos" hx-target="#todos">
-                        <option select="selected" value="All">
-                            All
-                        </option>
-                        <option value="Done">Done</option>
-                        <option value="NotDone">Not done</option>
-                    </select>
-                    <div class="flex flex-col" id="todos">
-                        {todos
-                            .into_iter()
-                            .map(|(id, todo)| view! { <Todo id todo/> })
-                            .collect_view()}
-                    </div>
-                    <hr class="w-full"/>
-                    <form hx-post="/todos" hx-target="#todos" hx-swap="beforeend">
-                        <input type="text" name="content"/>
-                        <button class="bg-teal-200 rounded-md p-2" type="submit">
-                            Add new
-                        </button>
-                    </form>
-                </body>
-            }
-        })
-        .into_owned(),
-    )
-}
-
-#[derive(Deserialize)]
-#[serde(tag = "sort")]
-enum GetTodosForm {
-    All,
-    Done,
-    NotDone,
-}
+    tokio::spawn(reminders::run(
+        todos.clone(),
+        Duration::from_secs(config.reminder_interval_secs.get()),
+        config.reminder_webhook_url.clone(),
+    ));
+    tokio::spawn(trash::run(
+        todos.clone(),
+        Duration::from_secs(config.trash_purge_interval_secs.get()),
+        Duration::from_secs(config.trash_retention_secs),
+    ));
 
-async fn get_todos(session: Session, Form(form): Form<GetTodosForm>) -> impl IntoResponse {
-    let todos: BTreeMap<usize, Todo> = session.get(TODOS_KEY).await.unwrap().unwrap();
-    let filter: Box<dyn Fn(&(usize, Todo)) -> bool> = match form {
-        GetTodosForm::All => Box::new(|_| true),
-        GetTodosForm::Done => Box::new(|(_, todo)| todo.done),
-        GetTodosForm::NotDone => Box::new(|(_, todo)| !todo.done),
+    let bind_addr = config.bind_addr.clone();
+    let state = AppState {
+        config: Arc::new(config),
+        todos,
+        lists: Arc::new(SqliteListRepository::new(pool.clone())),
+        members: Arc::new(SqliteMemberRepository::new(pool.clone())),
+        users: Arc::new(SqliteUserRepository::new(pool)),
+        events: Arc::new(ListEvents::default()),
+        presence: Arc::new(Presence::default()),
     };
 
-    Html(
-        leptos::ssr::render_to_string(move || {
-            todos
-                .into_iter()
-                .filter(|el| filter(el))
-                .map(|(id, todo)| view! { <Todo id todo/> })
-                .collect_view()
+    let app = routes()
+        .into_iter()
+        .fold(Router::new(), |router, route| {
+            tracing::debug!("route {} {}", route.method, route.path);
+            router.route(route.path, route.method_router)
         })
-        .into_owned(),
-    )
-}
-
-async fn put_todo(
-    Path(id): Path<usize>,
-    session: Session,
-) -> Result<impl IntoResponse, ApplicationError> {
-    let mut todos: BTreeMap<usize, Todo> = session.get(TODOS_KEY).await.unwrap().unwrap();
-
-    let todo = todos.get_mut(&id).ok_or(ApplicationError::NotFound)?;
-    todo.done = !todo.done;
-    let todo = todo.clone();
-
-    session.insert(TODOS_KEY, todos).await.unwrap();
-
-    Ok(Html(
-        leptos::ssr::render_to_string(move || {
-            view! { <Todo id=id todo=todo/> }
-        })
-        .into_owned(),
-    ))
-}
+        .fallback(|| async { ApplicationError::NotFound })
+        .layer(middleware::from_fn(errors::render_errors))
+        .layer(TraceLayer::new_for_http())
+        .layer(session_layer)
+        .with_state(state);
 
-#[derive(Deserialize)]
-struct CreateTodoForm {
-    content: String,
+    let listener = tokio::net::TcpListener::bind(&bind_addr).await.unwrap();
+    axum::serve(listener, app).await.unwrap();
 }
 
-async fn create_todo(session: Session, Form(form): Form<CreateTodoForm>) -> impl IntoResponse {
-    let todo = Todo {
-        content: form.content,
-        done: false,
-    };
-    let mut todos: BTreeMap<usize, Todo> = session.get(TODOS_KEY).await.unwrap().unwrap();
-    let i: usize = session.get(INDEX_KEY).await.unwrap().unwrap();
-    session.insert(INDEX_KEY, i + 1).await.unwrap();
-    todos.insert(i + 1, todo.clone());
-    session.insert(TODOS_KEY, todos).await.unwrap();
-    Html(leptos::ssr::render_to_string(move || view! { <Todo id=i + 1 todo=todo/> }).into_owned())
+struct Route {
+    method: Method,
+    path: &'static str,
+    method_router: MethodRouter<AppState>,
 }
 
-async fn delete_todo(
-    session: Session,
-    Path(id): Path<usize>,
-) -> Result<impl IntoResponse, ApplicationError> {
-    let mut todos: BTreeMap<usize, Todo> = session.get(TODOS_KEY).await.unwrap().unwrap();
-    todos.remove(&id).ok_or(ApplicationError::NotFound)?;
-    session.insert(TODOS_KEY, todos).await.unwrap();
-    Ok(())
+fn route<H, T>(method: Method, path: &'static str, handler: H) -> Route
+where
+    H: Handler<T, AppState>,
+    T: 'static,
+{
+    let filter = MethodFilter::try_from(method.clone()).expect("unsupported route method");
+    Route {
+        method,
+        path,
+        method_router: on(filter, handler),
+    }
 }
 
-#[component]
-fn Todo(id: usize, todo: Todo) -> impl IntoView {
-    view! {
-        <div id=format!("todo-{id}") class="w-full">
-            <hr class="w-full"/>
-            <div class="flex flex-row justify-between w-full text-xl">
-                <p>{todo.content}</p>
-                <p>{if todo.done { "done" } else { "not done" }}</p>
-                <button
-                    hx-put=format!("todos/{id}")
-                    hx-target=format!("#todo-{id}")
-                    hx-swap="outerHTML"
-                >
-                    Mark as done
-                </button>
-                <button
-                    hx-delete=format!("todos/{id}")
-                    hx-target=format!("#todo-{id}")
-                    hx-swap="delete"
-                >
-                    Delete
-                </button>
-            </div>
-        </div>
-    }
+/// Every route of the application. Each of them must be described by the OpenAPI document,
+/// which is checked by the tests of [`openapi`].
+fn routes() -> Vec<Route> {
+    vec![
+        route(Method::GET, "/", lists::root),
+        route(Method::GET, "/login", auth::login_page),
+        route(Method::POST, "/login", auth::login),
+        route(Method::GET, "/register", auth::register_page),
+        route(Method::POST, "/register", auth::register),
+        route(Method::POST, "/logout", auth::logout),
+        route(Method::POST, "/lists", lists::create_list),
+        route(Method::GET, "/lists/:list_id", lists::list_page),
+        route(Method::PUT, "/lists/:list_id", lists::rename_list),
+        route(Method::DELETE, "/lists/:list_id", lists::delete_list),
+        route(Method::GET, "/lists/:list_id/events", events::list_events),
+        route(
+            Method::GET,
+            "/lists/:list_id/presence",
+            presence::presence_socket,
+        ),
+        route(
+            Method::PUT,
+            "/lists/:list_id/members/:user_id",
+            members::update_member,
+        ),
+        route(
+            Method::DELETE,
+            "/lists/:list_id/members/:user_id",
+            members::remove_member,
+        ),
+        route(
+            Method::POST,
+            "/lists/:list_id/invites",
+            members::create_invite,
+        ),
+        route(Method::GET, "/invites/:token", members::invite_page),
+        route(Method::POST, "/invites/:token", members::accept_invite),
+        route(Method::GET, "/lists/:list_id/todos", todos::get_todos),
+        route(Method::POST, "/lists/:list_id/todos", todos::create_todo),
+        route(
+            Method::POST,
+            "/lists/:list_id/todos/reorder",
+            todos::reorder_todos,
+        ),
+        route(
+            Method::POST,
+            "/lists/:list_id/todos/bulk",
+            bulk::bulk_action,
+        ),
+        route(
+            Method::POST,
+            "/lists/:list_id/todos/toggle-all",
+            bulk::toggle_all,
+        ),
+        route(
+            Method::POST,
+            "/lists/:list_id/todos/clear-completed",
+            bulk::clear_completed,
+        ),
+        route(Method::GET, "/lists/:list_id/todos/:id", todos::get_todo),
+        route(Method::PUT, "/lists/:list_id/todos/:id", todos::put_todo),
+        route(
+            Method::PATCH,
+            "/lists/:list_id/todos/:id",
+            todos::patch_todo,
+        ),
+        route(
+            Method::DELETE,
+            "/lists/:list_id/todos/:id",
+            todos::delete_todo,
+        ),
+        route(
+            Method::GET,
+            "/lists/:list_id/todos/:id/edit",
+            todos::edit_todo,
+        ),
+        route(
+            Method::POST,
+            "/lists/:list_id/todos/:id/restore",
+            trash::restore_todo,
+        ),
+        route(Method::GET, "/lists/:list_id/trash", trash::get_trash),
+        route(Method::GET, "/lists/:list_id/footer", footer::get_footer),
+        route(Method::GET, "/api/openapi.json", openapi::openapi_json),
+        route(Method::GET, "/api/v1/lists", api::list_lists),
+        route(Method::POST, "/api/v1/lists", api::create_list),
+        route(Method::GET, "/api/v1/lists/:list_id", api::get_list),
+        route(Method::PATCH, "/api/v1/lists/:list_id", api::rename_list),
+        route(Method::DELETE, "/api/v1/lists/:list_id", api::delete_list),
+        route(Method::GET, "/api/v1/lists/:list_id/todos", api::list_todos),
+        route(
+            Method::POST,
+            "/api/v1/lists/:list_id/todos",
+            api::create_todo,
+        ),
+        route(
+            Method::GET,
+            "/api/v1/lists/:list_id/todos/:id",
+            api::get_todo,
+        ),
+        route(
+            Method::PATCH,
+            "/api/v1/lists/:list_id/todos/:id",
+            api::update_todo,
+        ),
+        route(
+            Method::DELETE,
+            "/api/v1/lists/:list_id/todos/:id",
+            api::delete_todo,
+        ),
+        route(
+            Method::POST,
+            "/api/v1/lists/:list_id/todos/:id/restore",
+            api::restore_todo,
+        ),
+    ]
 }
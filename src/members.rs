@@ -0,0 +1,393 @@
+//! Sharing of lists: the members of a list with their [`Role`], and the invite links through which
+//! other users join it.
+
+use axum::{
+    extract::State,
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use chrono::Utc;
+use leptos::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    auth::AuthUser,
+    components::Page,
+    errors::ApplicationError,
+    extract::{Form, Path},
+    lists::{CurrentList, TodoList},
+    AppState,
+};
+
+/// What a member can do on a list, each role allowing everything the previous ones do.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, sqlx::Type, ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum Role {
+    /// Sees the todos.
+    Viewer,
+    /// Adds, edits and deletes the todos.
+    Editor,
+    /// Renames and deletes the list, and manages its members.
+    Owner,
+}
+
+impl Role {
+    const ALL: [Role; 3] = [Role::Owner, Role::Editor, Role::Viewer];
+
+    /// Value of the role in forms.
+    fn value(self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Editor => "editor",
+            Role::Owner => "owner",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Role::Viewer => "Viewer",
+            Role::Editor => "Editor",
+            Role::Owner => "Owner",
+        }
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Member {
+    pub user_id: i64,
+    pub username: String,
+    pub role: Role,
+}
+
+/// Link giving whoever follows it before `expires_at` (a Unix timestamp) access to a list.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Invite {
+    pub token: String,
+    pub list_id: i64,
+    pub list_name: String,
+    pub role: Role,
+    pub expires_at: i64,
+}
+
+const LAST_OWNER: &str = "A list needs at least one owner";
+
+#[derive(Deserialize, ToSchema)]
+pub struct RoleForm {
+    role: Role,
+}
+
+#[utoipa::path(
+    put,
+    path = "/lists/{list_id}/members/{user_id}",
+    params(
+        ("list_id" = i64, Path, description = "Id of the list"),
+        ("user_id" = i64, Path, description = "Id of the member")
+    ),
+    request_body(content = RoleForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "The members panel, or `HX-Refresh` when owners demote themselves", content_type = "text/html"),
+        (status = 400, description = "The list would have no owner left", content_type = "text/html"),
+        (status = 403, description = "Only owners manage the members", content_type = "text/html"),
+        (status = 404, description = "No such list or member", content_type = "text/html")
+    )
+)]
+pub async fn update_member(
+    State(state): State<AppState>,
+    user: AuthUser,
+    CurrentList(list): CurrentList,
+    Path((_, user_id)): Path<(i64, i64)>,
+    Form(form): Form<RoleForm>,
+) -> Result<Response, ApplicationError> {
+    list.require(Role::Owner)?;
+    let mut members = state.members.list(list.id).await?;
+    let member = members
+        .iter_mut()
+        .find(|member| member.user_id == user_id)
+        .ok_or(ApplicationError::NotFound)?;
+    member.role = form.role;
+    if !members.iter().any(|member| member.role == Role::Owner) {
+        return Err(ApplicationError::BadRequest(LAST_OWNER.to_owned()));
+    }
+
+    if !state.members.set_role(list.id, user_id, form.role).await? {
+        return Err(ApplicationError::NotFound);
+    }
+    if user_id == user.id && form.role != Role::Owner {
+        return Ok([("HX-Refresh", "true")].into_response());
+    }
+    Ok(render_members(list, user.id, members))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/lists/{list_id}/members/{user_id}",
+    params(
+        ("list_id" = i64, Path, description = "Id of the list"),
+        ("user_id" = i64, Path, description = "Id of the member")
+    ),
+    responses(
+        (status = 200, description = "The members panel, or `HX-Redirect` to the first list when leaving it", content_type = "text/html"),
+        (status = 400, description = "The list would have no owner left", content_type = "text/html"),
+        (status = 403, description = "Only owners remove other members", content_type = "text/html"),
+        (status = 404, description = "No such list or member", content_type = "text/html")
+    )
+)]
+pub async fn remove_member(
+    State(state): State<AppState>,
+    user: AuthUser,
+    CurrentList(list): CurrentList,
+    Path((_, user_id)): Path<(i64, i64)>,
+) -> Result<Response, ApplicationError> {
+    // Anyone can leave a list, only owners remove the others
+    if user_id != user.id {
+        list.require(Role::Owner)?;
+    }
+    let mut members = state.members.list(list.id).await?;
+    members.retain(|member| member.user_id != user_id);
+    if !members.iter().any(|member| member.role == Role::Owner) {
+        return Err(ApplicationError::BadRequest(LAST_OWNER.to_owned()));
+    }
+
+    if !state.members.remove(list.id, user_id).await? {
+        return Err(ApplicationError::NotFound);
+    }
+    if user_id == user.id {
+        return Ok([("HX-Redirect", "/")].into_response());
+    }
+    Ok(render_members(list, user.id, members))
+}
+
+fn render_members(list: TodoList, user_id: i64, members: Vec<Member>) -> Response {
+    Html(
+        leptos::ssr::render_to_string(move || view! { <MembersPanel list user_id members/> })
+            .into_owned(),
+    )
+    .into_response()
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{list_id}/invites",
+    params(("list_id" = i64, Path, description = "Id of the list")),
+    request_body(content = RoleForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "The new invite link", content_type = "text/html"),
+        (status = 403, description = "Only owners invite members", content_type = "text/html"),
+        (status = 404, description = "No such list", content_type = "text/html")
+    )
+)]
+pub async fn create_invite(
+    State(state): State<AppState>,
+    CurrentList(list): CurrentList,
+    Form(form): Form<RoleForm>,
+) -> Result<impl IntoResponse, ApplicationError> {
+    list.require(Role::Owner)?;
+    let invite = Invite {
+        token: Uuid::new_v4().simple().to_string(),
+        list_id: list.id,
+        list_name: list.name,
+        role: form.role,
+        expires_at: Utc::now().timestamp() + state.config.invite_expiry_secs,
+    };
+    state.members.create_invite(&invite).await?;
+
+    let url = format!("{}/invites/{}", state.config.public_url, invite.token);
+    let hours = state.config.invite_expiry_secs / 3600;
+    Ok(Html(
+        leptos::ssr::render_to_string(move || {
+            view! { <InviteLink url role=invite.role hours/> }
+        })
+        .into_owned(),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/invites/{token}",
+    params(("token" = String, Path, description = "Token of the invite link")),
+    responses(
+        (status = 200, description = "The page to accept the invite", content_type = "text/html"),
+        (status = 303, description = "Already a member, redirects to the list"),
+        (status = 404, description = "No such invite, or it expired", content_type = "text/html")
+    )
+)]
+pub async fn invite_page(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(token): Path<String>,
+) -> Result<Response, ApplicationError> {
+    let invite = state
+        .members
+        .find_invite(&token, Utc::now().timestamp())
+        .await?
+        .ok_or(ApplicationError::NotFound)?;
+    if state.lists.get(user.id, invite.list_id).await?.is_some() {
+        return Ok(Redirect::to(&format!("/lists/{}", invite.list_id)).into_response());
+    }
+
+    Ok(Html(
+        leptos::ssr::render_to_string(move || view! { <InvitePage user invite/> }).into_owned(),
+    )
+    .into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/invites/{token}",
+    params(("token" = String, Path, description = "Token of the invite link")),
+    responses(
+        (status = 303, description = "Joined the list, redirects to it"),
+        (status = 404, description = "No such invite, or it expired", content_type = "text/html")
+    )
+)]
+pub async fn accept_invite(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(token): Path<String>,
+) -> Result<impl IntoResponse, ApplicationError> {
+    let invite = state
+        .members
+        .find_invite(&token, Utc::now().timestamp())
+        .await?
+        .ok_or(ApplicationError::NotFound)?;
+    state
+        .members
+        .join(invite.list_id, user.id, invite.role)
+        .await?;
+    Ok(Redirect::to(&format!("/lists/{}", invite.list_id)))
+}
+
+/// Members of the list. Owners change their roles, remove them and create invite links, the
+/// other members can only leave the list.
+#[component]
+pub fn MembersPanel(list: TodoList, user_id: i64, members: Vec<Member>) -> impl IntoView {
+    let owner = list.role == Role::Owner;
+    let list_id = list.id;
+    view! {
+        <section id="members" class="flex flex-col gap-2">
+            <h2 class="text-xl">Members</h2>
+            {members
+                .into_iter()
+                .map(|member| {
+                    let url = format!("/lists/{list_id}/members/{}", member.user_id);
+                    let me = member.user_id == user_id;
+                    view! {
+                        <div class="flex flex-row gap-2 items-center">
+                            <span class:font-bold=me>{member.username}</span>
+                            {if owner {
+                                view! {
+                                    <select
+                                        name="role"
+                                        hx-put=url.clone()
+                                        hx-trigger="change"
+                                        hx-target="#members"
+                                        hx-swap="outerHTML"
+                                    >
+                                        <RoleOptions selected=member.role/>
+                                    </select>
+                                }
+                                    .into_view()
+                            } else {
+                                view! { <span>{member.role.label()}</span> }.into_view()
+                            }}
+                            {(owner || me)
+                                .then(|| {
+                                    view! {
+                                        <button
+                                            class="underline"
+                                            hx-delete=url
+                                            hx-target="#members"
+                                            hx-swap="outerHTML"
+                                            hx-confirm=if me {
+                                                "Leave this list?"
+                                            } else {
+                                                "Remove this member from the list?"
+                                            }
+                                        >
+                                            {if me { "Leave" } else { "Remove" }}
+                                        </button>
+                                    }
+                                })}
+                        </div>
+                    }
+                })
+                .collect_view()}
+            {owner
+                .then(|| {
+                    view! {
+                        <form
+                            class="flex flex-row gap-2 items-center"
+                            hx-post=format!("/lists/{list_id}/invites")
+                            hx-target="#invite-link"
+                        >
+                            <select name="role">
+                                <RoleOptions selected=Role::Editor/>
+                            </select>
+                            <button class="bg-teal-200 rounded-md p-2" type="submit">
+                                Create invite link
+                            </button>
+                        </form>
+                        <div id="invite-link"></div>
+                    }
+                })}
+        </section>
+    }
+}
+
+#[component]
+fn RoleOptions(selected: Role) -> impl IntoView {
+    Role::ALL
+        .into_iter()
+        .map(|role| {
+            view! {
+                <option value=role.value() selected=role == selected>
+                    {role.label()}
+                </option>
+            }
+        })
+        .collect_view()
+}
+
+#[component]
+fn InviteLink(url: String, role: Role, hours: i64) -> impl IntoView {
+    view! {
+        <div class="flex flex-col gap-1">
+            <input type="text" readonly value=url onclick="this.select()"/>
+            <p class="text-sm">
+                {format!(
+                    "Anyone with this link can join the list as {} during the next {hours} hours.",
+                    role.value(),
+                )}
+            </p>
+        </div>
+    }
+}
+
+#[component]
+fn InvitePage(user: AuthUser, invite: Invite) -> impl IntoView {
+    view! {
+        <Page>
+            <h1 class="text-3xl">TodoMVC</h1>
+            <p>
+                {format!(
+                    "{}, you are invited to join the list \"{}\" as {}.",
+                    user.username,
+                    invite.list_name,
+                    invite.role.value(),
+                )}
+            </p>
+            <form method="post" action=format!("/invites/{}", invite.token)>
+                <button class="bg-teal-200 rounded-md p-2" type="submit">
+                    Join the list
+                </button>
+            </form>
+            <a class="underline" href="/">
+                Back to my lists
+            </a>
+        </Page>
+    }
+}
@@ -0,0 +1,156 @@
+use axum::{response::IntoResponse, Json};
+use utoipa::OpenApi;
+
+use crate::{
+    api, auth, bulk, errors::ErrorBody, events, footer, lists, members, presence, todos, trash,
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "TodoMVC",
+        description = "HTML fragments for htmx and the JSON API"
+    ),
+    paths(
+        lists::root,
+        lists::list_page,
+        lists::create_list,
+        lists::rename_list,
+        lists::delete_list,
+        events::list_events,
+        presence::presence_socket,
+        members::update_member,
+        members::remove_member,
+        members::create_invite,
+        members::invite_page,
+        members::accept_invite,
+        auth::login_page,
+        auth::login,
+        auth::register_page,
+        auth::register,
+        auth::logout,
+        todos::get_todos,
+        todos::create_todo,
+        todos::reorder_todos,
+        bulk::bulk_action,
+        bulk::toggle_all,
+        bulk::clear_completed,
+        todos::get_todo,
+        todos::put_todo,
+        todos::patch_todo,
+        todos::delete_todo,
+        todos::edit_todo,
+        trash::restore_todo,
+        trash::get_trash,
+        footer::get_footer,
+        openapi_json,
+        api::list_lists,
+        api::create_list,
+        api::get_list,
+        api::rename_list,
+        api::delete_list,
+        api::list_todos,
+        api::create_todo,
+        api::get_todo,
+        api::update_todo,
+        api::delete_todo,
+        api::restore_todo,
+    ),
+    components(schemas(
+        todos::Todo,
+        todos::Filter,
+        todos::DueFilter,
+        todos::Order,
+        todos::Due,
+        todos::Priority,
+        todos::Progress,
+        todos::CreateTodoForm,
+        todos::EditTodoForm,
+        todos::SetDoneForm,
+        todos::ReorderForm,
+        todos::GetTodosForm,
+        bulk::BulkAction,
+        bulk::BulkForm,
+        lists::TodoList,
+        lists::ListForm,
+        members::Role,
+        members::RoleForm,
+        auth::CredentialsForm,
+        api::TodoResponse,
+        api::UpdateTodoRequest,
+        ErrorBody,
+    ))
+)]
+pub struct ApiDoc;
+
+#[utoipa::path(
+    get,
+    path = "/api/openapi.json",
+    responses((status = 200, description = "This OpenAPI document", content_type = "application/json"))
+)]
+pub async fn openapi_json() -> impl IntoResponse {
+    Json(ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Method;
+    use utoipa::{openapi::PathItemType, OpenApi};
+
+    use super::ApiDoc;
+
+    /// `/todos/:id` in axum is `/todos/{id}` in OpenAPI.
+    fn openapi_path(path: &str) -> String {
+        path.split('/')
+            .map(|segment| match segment.strip_prefix(':') {
+                Some(param) => format!("{{{param}}}"),
+                None => segment.to_owned(),
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    fn path_item_type(method: &Method) -> PathItemType {
+        match *method {
+            Method::GET => PathItemType::Get,
+            Method::POST => PathItemType::Post,
+            Method::PUT => PathItemType::Put,
+            Method::PATCH => PathItemType::Patch,
+            Method::DELETE => PathItemType::Delete,
+            _ => panic!("unexpected route method {method}"),
+        }
+    }
+
+    #[test]
+    fn every_route_is_documented() {
+        let doc = ApiDoc::openapi();
+        for route in crate::routes() {
+            let path = openapi_path(route.path);
+            let documented =
+                doc.paths.paths.get(&path).is_some_and(|item| {
+                    item.operations.contains_key(&path_item_type(&route.method))
+                });
+            assert!(
+                documented,
+                "{} {} is not documented",
+                route.method, route.path
+            );
+        }
+    }
+
+    #[test]
+    fn every_documented_operation_is_routed() {
+        let routes = crate::routes()
+            .into_iter()
+            .map(|route| (openapi_path(route.path), path_item_type(&route.method)))
+            .collect::<Vec<_>>();
+        for (path, item) in ApiDoc::openapi().paths.paths {
+            for method in item.operations.keys() {
+                assert!(
+                    routes.contains(&(path.clone(), method.clone())),
+                    "{path} documents an operation that is not routed"
+                );
+            }
+        }
+    }
+}
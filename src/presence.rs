@@ -0,0 +1,285 @@
+//! Presence on the list pages: who has a list open and who is editing which todo.
+//!
+//! Each list page keeps a WebSocket open, through which it is sent the users on the list and the
+//! soft locks on its todos as fragments for the htmx WebSocket extension. A page takes the lock of
+//! a todo when opening its editor and releases it when saving, cancelling or closing the page.
+//! Other pages show who holds the lock and cannot open the editor meanwhile, the other changes to
+//! the todo are still allowed.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Mutex,
+};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        State, WebSocketUpgrade,
+    },
+    response::IntoResponse,
+};
+use leptos::*;
+use serde::Deserialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+use utoipa::IntoParams;
+
+use crate::{
+    auth::AuthUser, errors::ApplicationError, extract::Query, lists::CurrentList, AppState,
+};
+
+/// Events a page can fall behind by before missing some.
+const CHANNEL_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone)]
+enum PresenceEvent {
+    /// The users who have the list open changed.
+    Users(Vec<String>),
+    /// The todo was locked by `client` for `username`, or unlocked.
+    Lock {
+        todo_id: i64,
+        client: String,
+        username: Option<String>,
+    },
+}
+
+struct ListPresence {
+    sender: broadcast::Sender<PresenceEvent>,
+    /// Username of each connected page, by client id.
+    clients: HashMap<String, String>,
+    /// Client id holding the lock of each todo being edited.
+    locks: HashMap<i64, String>,
+}
+
+impl ListPresence {
+    fn users(&self) -> Vec<String> {
+        let users: BTreeSet<_> = self.clients.values().cloned().collect();
+        users.into_iter().collect()
+    }
+
+    fn send(&self, event: PresenceEvent) {
+        // Nobody may be listening anymore, the list is dropped when its last page leaves
+        let _ = self.sender.send(event);
+    }
+}
+
+#[derive(Default)]
+pub struct Presence {
+    lists: Mutex<HashMap<i64, ListPresence>>,
+}
+
+impl Presence {
+    fn join(
+        &self,
+        list_id: i64,
+        client: &str,
+        username: &str,
+    ) -> broadcast::Receiver<PresenceEvent> {
+        let mut lists = self.lists.lock().unwrap();
+        let list = lists.entry(list_id).or_insert_with(|| ListPresence {
+            sender: broadcast::channel(CHANNEL_CAPACITY).0,
+            clients: HashMap::new(),
+            locks: HashMap::new(),
+        });
+        let receiver = list.sender.subscribe();
+        list.clients.insert(client.to_owned(), username.to_owned());
+        list.send(PresenceEvent::Users(list.users()));
+        receiver
+    }
+
+    /// Releases the locks the page held.
+    fn leave(&self, list_id: i64, client: &str) {
+        let mut lists = self.lists.lock().unwrap();
+        let Some(list) = lists.get_mut(&list_id) else {
+            return;
+        };
+        list.clients.remove(client);
+        if list.clients.is_empty() {
+            lists.remove(&list_id);
+            return;
+        }
+
+        let released: Vec<i64> = list
+            .locks
+            .iter()
+            .filter(|(_, holder)| holder.as_str() == client)
+            .map(|(todo_id, _)| *todo_id)
+            .collect();
+        for todo_id in released {
+            list.locks.remove(&todo_id);
+            list.send(PresenceEvent::Lock {
+                todo_id,
+                client: client.to_owned(),
+                username: None,
+            });
+        }
+        list.send(PresenceEvent::Users(list.users()));
+    }
+
+    /// Takes the lock of the todo for the page, failing with the name of the user holding it when
+    /// another page does. Pages without a connection take no lock, as nothing would release it.
+    pub fn lock(&self, list_id: i64, todo_id: i64, client: Option<&str>) -> Result<(), String> {
+        let mut lists = self.lists.lock().unwrap();
+        let Some(list) = lists.get_mut(&list_id) else {
+            return Ok(());
+        };
+        if let Some(holder) = list.locks.get(&todo_id) {
+            if Some(holder.as_str()) != client {
+                return Err(list.clients[holder].clone());
+            }
+        }
+
+        let Some((client, username)) = client.and_then(|client| list.clients.get_key_value(client))
+        else {
+            return Ok(());
+        };
+        let (client, username) = (client.clone(), username.clone());
+        list.locks.insert(todo_id, client.clone());
+        list.send(PresenceEvent::Lock {
+            todo_id,
+            client,
+            username: Some(username),
+        });
+        Ok(())
+    }
+
+    /// Releases the lock of the todo, if the page holds it.
+    pub fn unlock(&self, list_id: i64, todo_id: i64, client: Option<&str>) {
+        let mut lists = self.lists.lock().unwrap();
+        let Some(list) = lists.get_mut(&list_id) else {
+            return;
+        };
+        let Some(client) = client else {
+            return;
+        };
+        if list.locks.get(&todo_id).map(String::as_str) == Some(client) {
+            list.locks.remove(&todo_id);
+            list.send(PresenceEvent::Lock {
+                todo_id,
+                client: client.to_owned(),
+                username: None,
+            });
+        }
+    }
+
+    /// Name of the user editing the todo, if any.
+    pub fn editor(&self, list_id: i64, todo_id: i64) -> Option<String> {
+        let lists = self.lists.lock().unwrap();
+        let list = lists.get(&list_id)?;
+        let holder = list.locks.get(&todo_id)?;
+        list.clients.get(holder).cloned()
+    }
+
+    /// Name of the user editing each todo being edited.
+    pub fn editors(&self, list_id: i64) -> HashMap<i64, String> {
+        let lists = self.lists.lock().unwrap();
+        let Some(list) = lists.get(&list_id) else {
+            return HashMap::new();
+        };
+        list.locks
+            .iter()
+            .filter_map(|(todo_id, holder)| Some((*todo_id, list.clients.get(holder)?.clone())))
+            .collect()
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PresenceQuery {
+    /// [`ClientId`](crate::events::ClientId) of the connecting page.
+    client: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/lists/{list_id}/presence",
+    params(("list_id" = i64, Path, description = "Id of the list"), PresenceQuery),
+    responses(
+        (status = 101, description = "WebSocket sending the users on the list and the locks on its todos, as fragments swapped by id"),
+        (status = 404, description = "No such list", content_type = "text/html")
+    )
+)]
+pub async fn presence_socket(
+    State(state): State<AppState>,
+    user: AuthUser,
+    CurrentList(list): CurrentList,
+    Query(query): Query<PresenceQuery>,
+    ws: WebSocketUpgrade,
+) -> Result<impl IntoResponse, ApplicationError> {
+    Ok(ws.on_upgrade(move |socket| connect(socket, state, list.id, user, query.client)))
+}
+
+async fn connect(
+    mut socket: WebSocket,
+    state: AppState,
+    list_id: i64,
+    user: AuthUser,
+    client: String,
+) {
+    let mut events = state.presence.join(list_id, &client, &user.username);
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    // Users removed from the list since the page connected are disconnected
+                    if !matches!(state.lists.get(user.id, list_id).await, Ok(Some(_))) {
+                        break;
+                    }
+                    let Some(html) = render_event(event, &client) else {
+                        continue;
+                    };
+                    if socket.send(Message::Text(html)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            },
+            // The pages send nothing, only the end of the connection matters
+            message = socket.recv() => match message {
+                Some(Ok(_)) => continue,
+                _ => break,
+            },
+        }
+    }
+    state.presence.leave(list_id, &client);
+}
+
+fn render_event(event: PresenceEvent, client: &str) -> Option<String> {
+    let html = match event {
+        PresenceEvent::Users(users) => {
+            leptos::ssr::render_to_string(move || view! { <OnlineUsers users/> })
+        }
+        // The page holding the lock shows the editor instead of the row
+        PresenceEvent::Lock { client: holder, .. } if holder == client => return None,
+        PresenceEvent::Lock {
+            todo_id, username, ..
+        } => leptos::ssr::render_to_string(move || {
+            view! { <EditorBadge todo_id editor=username/> }
+        }),
+    };
+    Some(html.into_owned())
+}
+
+/// Users who have the list open.
+#[component]
+pub fn OnlineUsers(#[prop(optional)] users: Vec<String>) -> impl IntoView {
+    view! {
+        <div id="online-users" class="flex flex-row gap-2 items-center text-sm">
+            {(!users.is_empty()).then(|| view! { <span>{"Online:"}</span> })}
+            {users
+                .into_iter()
+                .map(|user| view! { <span class="bg-sky-100 rounded-full px-2">{user}</span> })
+                .collect_view()}
+        </div>
+    }
+}
+
+/// Badge of a [`Todo`](crate::todos::Todo) row naming the user editing it.
+#[component]
+pub fn EditorBadge(todo_id: i64, editor: Option<String>) -> impl IntoView {
+    view! {
+        <span id=format!("editor-{todo_id}") class="text-sm text-amber-700">
+            {editor.map(|editor| format!("{editor} is editing"))}
+        </span>
+    }
+}
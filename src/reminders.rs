@@ -0,0 +1,66 @@
+//! Reminders of the todos coming due, sent by a background task: each of them is logged and,
+//! when a webhook is configured, posted to it as JSON.
+//!
+//! A todo is reminded once per due date, even when posting it to the webhook fails.
+
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::repository::TodoRepository;
+
+/// Longest a webhook can hold up the reminders after the one being posted.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Reminder {
+    pub list_id: i64,
+    pub todo_id: i64,
+    pub content: String,
+    pub due_at: DateTime<Utc>,
+}
+
+/// Checks for the todos which came due every `interval`, until the process exits.
+pub async fn run(todos: Arc<dyn TodoRepository>, interval: Duration, webhook_url: Option<String>) {
+    let client = match reqwest::Client::builder().timeout(WEBHOOK_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::error!("cannot create the client posting the reminders: {e}");
+            return;
+        }
+    };
+    let mut ticks = tokio::time::interval(interval);
+    loop {
+        ticks.tick().await;
+        let reminders = match todos.take_reminders(Utc::now().timestamp()).await {
+            Ok(reminders) => reminders,
+            Err(e) => {
+                tracing::error!("cannot load the todos coming due: {e:#}");
+                continue;
+            }
+        };
+
+        for reminder in reminders {
+            tracing::info!(
+                list_id = reminder.list_id,
+                todo_id = reminder.todo_id,
+                due_at = %reminder.due_at,
+                "todo came due: {}",
+                reminder.content
+            );
+            let Some(url) = &webhook_url else {
+                continue;
+            };
+            let sent = client
+                .post(url)
+                .json(&reminder)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            if let Err(e) = sent {
+                tracing::warn!("cannot post the reminder of todo {}: {e}", reminder.todo_id);
+            }
+        }
+    }
+}
@@ -0,0 +1,444 @@
+//! [`TodoRepository`] keeping the todos in memory, to test the handlers without a database.
+
+use std::{collections::BTreeMap, sync::Mutex};
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::{
+    footer::TodoCounts,
+    reminders::Reminder,
+    repository::{StaleVersion, TodoRepository},
+    todos::{Progress, Todo},
+    trash::TrashedTodo,
+};
+
+struct StoredTodo {
+    list_id: i64,
+    todo: Todo,
+    /// Unix timestamp in milliseconds, set while the todo is in the trash.
+    deleted_at: Option<i64>,
+    reminded: bool,
+}
+
+#[derive(Default)]
+struct Todos {
+    last_id: i64,
+    todos: BTreeMap<i64, StoredTodo>,
+}
+
+impl Todos {
+    /// The todo of the list, unless it is in the trash.
+    fn live(&self, list_id: i64, id: i64) -> Option<&StoredTodo> {
+        self.todos
+            .get(&id)
+            .filter(|stored| stored.list_id == list_id && stored.deleted_at.is_none())
+    }
+
+    /// The todo with the progress of its subtasks.
+    fn load(&self, id: i64) -> Todo {
+        let mut todo = self.todos[&id].todo.clone();
+        let subtasks = self.children(id, |stored| stored.deleted_at.is_none());
+        todo.progress = Progress {
+            done: subtasks
+                .iter()
+                .filter(|id| self.todos[id].todo.done)
+                .count() as i64,
+            total: subtasks.len() as i64,
+        };
+        todo
+    }
+
+    fn children(&self, id: i64, keep: impl Fn(&StoredTodo) -> bool) -> Vec<i64> {
+        self.todos
+            .iter()
+            .filter(|(_, stored)| stored.todo.parent_id == Some(id) && keep(stored))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// The subtasks of the todo, at every depth, which `keep` accepts along with their parent.
+    fn descendants(&self, id: i64, keep: impl Fn(&StoredTodo) -> bool + Copy) -> Vec<i64> {
+        let mut descendants = self.children(id, keep);
+        let mut index = 0;
+        while let Some(id) = descendants.get(index).copied() {
+            descendants.extend(self.children(id, keep));
+            index += 1;
+        }
+        descendants
+    }
+
+    fn ancestors(&self, id: i64) -> Vec<i64> {
+        let mut ancestors = Vec::new();
+        let mut parent_id = self.todos.get(&id).and_then(|stored| stored.todo.parent_id);
+        while let Some(id) = parent_id {
+            ancestors.push(id);
+            parent_id = self.todos.get(&id).and_then(|stored| stored.todo.parent_id);
+        }
+        ancestors
+    }
+
+    fn next_position(&self, list_id: i64, parent_id: Option<i64>) -> f64 {
+        self.todos
+            .values()
+            .filter(|stored| stored.list_id == list_id && stored.todo.parent_id == parent_id)
+            .map(|stored| stored.todo.position)
+            .fold(0.0, f64::max)
+            + 1.0
+    }
+
+    /// Applies the update when `todo.version` is still the stored one, `None` otherwise or when
+    /// there is no such todo.
+    fn update(&mut self, list_id: i64, id: i64, todo: Todo) -> Option<Todo> {
+        self.live(list_id, id)
+            .filter(|stored| stored.todo.version == todo.version)?;
+        let stored = self.todos.get_mut(&id)?;
+        // A new due date needs a new reminder
+        stored.reminded &= stored.todo.due_at() == todo.due_at();
+        stored.todo = Todo {
+            version: todo.version + 1,
+            parent_id: stored.todo.parent_id,
+            position: stored.todo.position,
+            created_at: stored.todo.created_at,
+            updated_at: Utc::now(),
+            ..todo
+        };
+        Some(self.load(id))
+    }
+
+    /// Moves the todo and its subtasks to the trash, returns whether it was not there already.
+    fn delete_tree(&mut self, list_id: i64, id: i64, deleted_at: i64) -> bool {
+        if self.live(list_id, id).is_none() {
+            return false;
+        }
+        let mut deleted = self.descendants(id, |stored| stored.deleted_at.is_none());
+        deleted.push(id);
+        for id in deleted {
+            self.todos.get_mut(&id).unwrap().deleted_at = Some(deleted_at);
+        }
+        true
+    }
+
+    /// Takes the todo out of the trash, along with the subtasks deleted with it and its ancestors.
+    fn restore_tree(&mut self, list_id: i64, id: i64) -> bool {
+        let Some(deleted_at) = self
+            .todos
+            .get(&id)
+            .filter(|stored| stored.list_id == list_id)
+            .and_then(|stored| stored.deleted_at)
+        else {
+            return false;
+        };
+        let mut restored = self.descendants(id, |stored| stored.deleted_at == Some(deleted_at));
+        restored.push(id);
+        restored.extend(self.ancestors(id));
+        for id in restored {
+            self.todos.get_mut(&id).unwrap().deleted_at = None;
+        }
+        self.cascade_done(id, self.todos[&id].todo.done);
+        true
+    }
+
+    /// Completes the subtasks of a todo done, reopens the ancestors of one not done. Returns the
+    /// ids of the todos changed.
+    fn cascade_done(&mut self, id: i64, done: bool) -> Vec<i64> {
+        let cascaded = if done {
+            self.descendants(id, |stored| stored.deleted_at.is_none())
+        } else {
+            self.ancestors(id)
+        };
+        self.set_done(cascaded, done)
+    }
+
+    /// Sets `done` on the todos which are not already, bumping their version.
+    fn set_done(&mut self, ids: Vec<i64>, done: bool) -> Vec<i64> {
+        let mut changed = Vec::new();
+        for id in ids {
+            let stored = self.todos.get_mut(&id).unwrap();
+            if stored.todo.done != done {
+                stored.todo.done = done;
+                stored.todo.version += 1;
+                stored.todo.updated_at = Utc::now();
+                changed.push(id);
+            }
+        }
+        changed
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryTodoRepository {
+    todos: Mutex<Todos>,
+}
+
+#[async_trait]
+impl TodoRepository for MemoryTodoRepository {
+    async fn list(&self, list_id: i64) -> anyhow::Result<Vec<(i64, Todo)>> {
+        let todos = self.todos.lock().unwrap();
+        let mut ids: Vec<i64> = todos
+            .todos
+            .keys()
+            .copied()
+            .filter(|id| todos.live(list_id, *id).is_some())
+            .collect();
+        ids.sort_by(|a, b| {
+            let position = |id: &i64| todos.todos[id].todo.position;
+            position(a).total_cmp(&position(b)).then(a.cmp(b))
+        });
+        Ok(ids.into_iter().map(|id| (id, todos.load(id))).collect())
+    }
+
+    async fn counts(&self, list_id: i64) -> anyhow::Result<TodoCounts> {
+        let todos = self.todos.lock().unwrap();
+        let live = todos.todos.keys().filter_map(|id| todos.live(list_id, *id));
+        let (total, done) = live.fold((0, 0), |(total, done), stored| {
+            (total + 1, done + i64::from(stored.todo.done))
+        });
+        Ok(TodoCounts { total, done })
+    }
+
+    async fn get(&self, list_id: i64, id: i64) -> anyhow::Result<Option<Todo>> {
+        let todos = self.todos.lock().unwrap();
+        Ok(todos.live(list_id, id).map(|_| todos.load(id)))
+    }
+
+    async fn create(&self, list_id: i64, mut todo: Todo) -> anyhow::Result<i64> {
+        let mut todos = self.todos.lock().unwrap();
+        todos.last_id += 1;
+        let id = todos.last_id;
+        let done = todo.done;
+        todo.version = 0;
+        todo.position = todos.next_position(list_id, todo.parent_id);
+        todos.todos.insert(
+            id,
+            StoredTodo {
+                list_id,
+                todo,
+                deleted_at: None,
+                reminded: false,
+            },
+        );
+        todos.cascade_done(id, done);
+        Ok(id)
+    }
+
+    async fn update(
+        &self,
+        list_id: i64,
+        id: i64,
+        todo: Todo,
+    ) -> anyhow::Result<Option<(Todo, Vec<i64>)>> {
+        let mut todos = self.todos.lock().unwrap();
+        if let Some(todo) = todos.update(list_id, id, todo) {
+            let cascaded = todos.cascade_done(id, todo.done);
+            return Ok(Some((todos.load(id), cascaded)));
+        }
+        match todos.live(list_id, id) {
+            Some(_) => Err(StaleVersion.into()),
+            None => Ok(None),
+        }
+    }
+
+    async fn update_many(&self, list_id: i64, updates: Vec<(i64, Todo)>) -> anyhow::Result<()> {
+        let mut todos = self.todos.lock().unwrap();
+        let applies = updates.iter().all(|(id, todo)| {
+            todos
+                .live(list_id, *id)
+                .is_some_and(|stored| stored.todo.version == todo.version)
+        });
+        if !applies {
+            return Err(StaleVersion.into());
+        }
+        let updated: Vec<(i64, bool)> = updates.iter().map(|(id, todo)| (*id, todo.done)).collect();
+        for (id, todo) in updates {
+            todos.update(list_id, id, todo);
+        }
+        for (id, done) in updated {
+            todos.cascade_done(id, done);
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, list_id: i64, id: i64) -> anyhow::Result<Option<Todo>> {
+        let mut todos = self.todos.lock().unwrap();
+        let Some(todo) = todos.live(list_id, id).map(|_| todos.load(id)) else {
+            return Ok(None);
+        };
+        todos.delete_tree(list_id, id, Utc::now().timestamp_millis());
+        Ok(Some(todo))
+    }
+
+    async fn trash(&self, list_id: i64) -> anyhow::Result<Vec<TrashedTodo>> {
+        let todos = self.todos.lock().unwrap();
+        let mut trash: Vec<TrashedTodo> = todos
+            .todos
+            .iter()
+            .filter(|(_, stored)| stored.list_id == list_id)
+            .filter_map(|(id, stored)| Some((*id, stored, stored.deleted_at?)))
+            // Without the subtasks deleted along with their parent
+            .filter(|(_, stored, deleted_at)| {
+                stored
+                    .todo
+                    .parent_id
+                    .is_none_or(|parent_id| todos.todos[&parent_id].deleted_at != Some(*deleted_at))
+            })
+            .map(|(id, _, deleted_at)| TrashedTodo {
+                id,
+                todo: todos.load(id),
+                subtasks: todos
+                    .children(id, |stored| stored.deleted_at == Some(deleted_at))
+                    .len() as i64,
+                deleted_at: DateTime::from_timestamp_millis(deleted_at).unwrap_or_default(),
+            })
+            .collect();
+        trash.sort_by_key(|trashed| (std::cmp::Reverse(trashed.deleted_at), trashed.id));
+        Ok(trash)
+    }
+
+    async fn delete_many(&self, list_id: i64, ids: &[i64]) -> anyhow::Result<Vec<i64>> {
+        let mut todos = self.todos.lock().unwrap();
+        let deleted_at = Utc::now().timestamp_millis();
+        Ok(ids
+            .iter()
+            .copied()
+            .filter(|id| todos.delete_tree(list_id, *id, deleted_at))
+            .collect())
+    }
+
+    async fn restore(&self, list_id: i64, id: i64) -> anyhow::Result<Option<Todo>> {
+        let mut todos = self.todos.lock().unwrap();
+        Ok(todos.restore_tree(list_id, id).then(|| todos.load(id)))
+    }
+
+    async fn restore_many(&self, list_id: i64, ids: &[i64]) -> anyhow::Result<Vec<i64>> {
+        let mut todos = self.todos.lock().unwrap();
+        Ok(ids
+            .iter()
+            .copied()
+            .filter(|id| todos.restore_tree(list_id, *id))
+            .collect())
+    }
+
+    async fn purge(&self, before: i64) -> anyhow::Result<u64> {
+        let mut todos = self.todos.lock().unwrap();
+        let purged: Vec<i64> = todos
+            .todos
+            .iter()
+            .filter(|(_, stored)| stored.deleted_at.is_some_and(|at| at < before * 1000))
+            .map(|(id, _)| *id)
+            .collect();
+        // Without the subtasks deleted along with their parent
+        let count = purged
+            .iter()
+            .filter(|id| {
+                let stored = &todos.todos[*id];
+                stored
+                    .todo
+                    .parent_id
+                    .is_none_or(|parent_id| todos.todos[&parent_id].deleted_at != stored.deleted_at)
+            })
+            .count();
+        for id in purged {
+            todos.todos.remove(&id);
+        }
+        Ok(count as u64)
+    }
+
+    async fn move_after(&self, list_id: i64, id: i64, after: Option<i64>) -> anyhow::Result<bool> {
+        let mut todos = self.todos.lock().unwrap();
+        let Some(parent_id) = todos.live(list_id, id).map(|stored| stored.todo.parent_id) else {
+            return Ok(false);
+        };
+        let mut siblings: Vec<i64> = todos
+            .todos
+            .iter()
+            .filter(|(sibling, stored)| {
+                **sibling != id
+                    && stored.list_id == list_id
+                    && stored.deleted_at.is_none()
+                    && stored.todo.parent_id == parent_id
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        siblings.sort_by(|a, b| {
+            let position = |id: &i64| todos.todos[id].todo.position;
+            position(a).total_cmp(&position(b)).then(a.cmp(b))
+        });
+        let index = match after {
+            None => 0,
+            Some(after) => match siblings.iter().position(|id| *id == after) {
+                Some(index) => index + 1,
+                None => return Ok(false),
+            },
+        };
+        siblings.insert(index, id);
+        for (index, id) in siblings.into_iter().enumerate() {
+            todos.todos.get_mut(&id).unwrap().todo.position = index as f64 + 1.0;
+        }
+        Ok(true)
+    }
+
+    async fn move_to_list(
+        &self,
+        list_id: i64,
+        ids: &[i64],
+        to_list_id: i64,
+    ) -> anyhow::Result<Vec<i64>> {
+        let mut todos = self.todos.lock().unwrap();
+        let mut moved = Vec::new();
+        for id in ids {
+            if todos.live(list_id, *id).is_none() {
+                continue;
+            }
+            let position = todos.next_position(to_list_id, None);
+            let stored = todos.todos.get_mut(id).unwrap();
+            stored.list_id = to_list_id;
+            stored.todo.parent_id = None;
+            stored.todo.position = position;
+            stored.todo.version += 1;
+            stored.todo.updated_at = Utc::now();
+            for subtask in todos.descendants(*id, |_| true) {
+                todos.todos.get_mut(&subtask).unwrap().list_id = to_list_id;
+            }
+            moved.push(*id);
+        }
+        Ok(moved)
+    }
+
+    async fn take_reminders(&self, now: i64) -> anyhow::Result<Vec<Reminder>> {
+        let mut todos = self.todos.lock().unwrap();
+        let mut reminders = Vec::new();
+        for (id, stored) in &mut todos.todos {
+            let Some(due_at) = stored.todo.due_at() else {
+                continue;
+            };
+            if due_at.timestamp() <= now
+                && !stored.reminded
+                && !stored.todo.done
+                && stored.deleted_at.is_none()
+            {
+                stored.reminded = true;
+                reminders.push(Reminder {
+                    list_id: stored.list_id,
+                    todo_id: *id,
+                    content: stored.todo.content.clone(),
+                    due_at,
+                });
+            }
+        }
+        Ok(reminders)
+    }
+
+    /// There were never anonymous sessions here.
+    async fn adopt(&self, _owner: &str, _list_id: i64) -> anyhow::Result<u64> {
+        Ok(0)
+    }
+
+    async fn anonymous_owners(&self) -> anyhow::Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    async fn drop_anonymous(&self, _owner: &str) -> anyhow::Result<u64> {
+        Ok(0)
+    }
+}
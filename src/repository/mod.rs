@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod memory;
+mod sqlite;
+
+use std::fmt;
+
+use axum::async_trait;
+
+use crate::{
+    auth::User,
+    footer::TodoCounts,
+    lists::TodoList,
+    members::{Invite, Member, Role},
+    reminders::Reminder,
+    todos::Todo,
+    trash::TrashedTodo,
+};
+
+#[cfg(test)]
+pub use memory::MemoryTodoRepository;
+pub use sqlite::{
+    connect, SqliteListRepository, SqliteMemberRepository, SqliteTodoRepository,
+    SqliteUserRepository,
+};
+
+/// Storage for the todos of each list.
+///
+/// `Ok(None)` means the todo does not exist in that list.
+///
+/// `update` only applies when the stored version still matches `todo.version`, otherwise it
+/// fails with [`StaleVersion`]. Every successful update bumps the version. `update_many` applies
+/// all the updates or none, failing with [`StaleVersion`] as soon as one of them does not apply.
+/// The parent of a todo is set when creating it and only changes when `move_to_list` moves it.
+///
+/// `counts` counts the todos of a list, subtasks included. Todos come with the progress of their
+/// direct subtasks. A todo cannot be done before its subtasks: creating, updating or restoring a
+/// todo done marks all its subtasks done, and one not done marks all its ancestors not done, in
+/// the same transaction. `update` returns the ids of these other todos it changed.
+///
+/// Deleting a todo moves it to the trash along with its subtasks, the todos in the trash are left
+/// out everywhere else. `trash` lists the todos deleted, most recent first, without the subtasks
+/// deleted along with them. `restore` takes a todo out of the trash along with these subtasks and
+/// the ancestors in the trash, `Ok(None)` when it is not in the trash. `delete_many` and
+/// `restore_many` do the same for several todos at once and return the ids of those they found,
+/// the todos deleted together being restored together. `purge` deletes for good the todos of
+/// every list deleted before `before` (a Unix timestamp) and returns how many, not counting the
+/// subtasks going along with their parent.
+///
+/// Todos are listed in the order of their position among their siblings, new todos go last.
+/// `move_after` places a todo right after one of its siblings, or first without one, and returns
+/// whether both exist. `move_to_list` moves todos to the end of another list, with their
+/// subtasks, as top-level todos, and returns the ids of those it found.
+///
+/// `take_reminders` returns the todos of every list which came due by `now` (a Unix timestamp)
+/// and are not done, each of them only once per due date.
+///
+/// `adopt` moves the todos of the anonymous session `owner`, from before user accounts, to the end
+/// of a list and returns how many. `anonymous_owners` lists the anonymous sessions which still
+/// have todos set aside, `drop_anonymous` deletes those of one of them and returns how many.
+#[async_trait]
+pub trait TodoRepository: Send + Sync {
+    async fn list(&self, list_id: i64) -> anyhow::Result<Vec<(i64, Todo)>>;
+    async fn counts(&self, list_id: i64) -> anyhow::Result<TodoCounts>;
+    async fn get(&self, list_id: i64, id: i64) -> anyhow::Result<Option<Todo>>;
+    async fn create(&self, list_id: i64, todo: Todo) -> anyhow::Result<i64>;
+    async fn update(
+        &self,
+        list_id: i64,
+        id: i64,
+        todo: Todo,
+    ) -> anyhow::Result<Option<(Todo, Vec<i64>)>>;
+    async fn update_many(&self, list_id: i64, todos: Vec<(i64, Todo)>) -> anyhow::Result<()>;
+    async fn delete(&self, list_id: i64, id: i64) -> anyhow::Result<Option<Todo>>;
+    async fn trash(&self, list_id: i64) -> anyhow::Result<Vec<TrashedTodo>>;
+    async fn delete_many(&self, list_id: i64, ids: &[i64]) -> anyhow::Result<Vec<i64>>;
+    async fn restore(&self, list_id: i64, id: i64) -> anyhow::Result<Option<Todo>>;
+    async fn restore_many(&self, list_id: i64, ids: &[i64]) -> anyhow::Result<Vec<i64>>;
+    async fn purge(&self, before: i64) -> anyhow::Result<u64>;
+    async fn move_after(&self, list_id: i64, id: i64, after: Option<i64>) -> anyhow::Result<bool>;
+    async fn move_to_list(
+        &self,
+        list_id: i64,
+        ids: &[i64],
+        to_list_id: i64,
+    ) -> anyhow::Result<Vec<i64>>;
+    async fn take_reminders(&self, now: i64) -> anyhow::Result<Vec<Reminder>>;
+    async fn adopt(&self, owner: &str, list_id: i64) -> anyhow::Result<u64>;
+    async fn anonymous_owners(&self) -> anyhow::Result<Vec<String>>;
+    async fn drop_anonymous(&self, owner: &str) -> anyhow::Result<u64>;
+}
+
+/// Storage for the todo lists, which users reach through their membership.
+///
+/// `Ok(None)` means the list does not exist or the user is not a member of it. Lists come with
+/// the role of that user, creating a list makes its creator the owner. Deleting a list deletes its
+/// todos.
+#[async_trait]
+pub trait ListRepository: Send + Sync {
+    async fn list(&self, user_id: i64) -> anyhow::Result<Vec<TodoList>>;
+    async fn get(&self, user_id: i64, id: i64) -> anyhow::Result<Option<TodoList>>;
+    async fn create(&self, user_id: i64, name: &str) -> anyhow::Result<TodoList>;
+    async fn rename(&self, user_id: i64, id: i64, name: &str) -> anyhow::Result<Option<TodoList>>;
+    async fn delete(&self, user_id: i64, id: i64) -> anyhow::Result<Option<TodoList>>;
+}
+
+/// Storage for the members of each list and the invite links to join them.
+///
+/// `set_role` and `remove` return whether the user was a member.
+#[async_trait]
+pub trait MemberRepository: Send + Sync {
+    async fn list(&self, list_id: i64) -> anyhow::Result<Vec<Member>>;
+    async fn set_role(&self, list_id: i64, user_id: i64, role: Role) -> anyhow::Result<bool>;
+    async fn remove(&self, list_id: i64, user_id: i64) -> anyhow::Result<bool>;
+    async fn create_invite(&self, invite: &Invite) -> anyhow::Result<()>;
+    /// `Ok(None)` when there is no such invite or it expired before `now`.
+    async fn find_invite(&self, token: &str, now: i64) -> anyhow::Result<Option<Invite>>;
+    /// Adds the user to the list, users who are already members keep their role.
+    async fn join(&self, list_id: i64, user_id: i64, role: Role) -> anyhow::Result<()>;
+}
+
+/// Storage for the user accounts. Usernames are unique, regardless of their case.
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    /// `Ok(None)` when the username is already taken.
+    async fn create(&self, username: &str, password_hash: &str) -> anyhow::Result<Option<User>>;
+    async fn find_by_username(&self, username: &str) -> anyhow::Result<Option<User>>;
+}
+
+/// The todo was modified since the version the client based its change on.
+#[derive(Debug)]
+pub struct StaleVersion;
+
+impl fmt::Display for StaleVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("stale todo version")
+    }
+}
+
+impl std::error::Error for StaleVersion {}
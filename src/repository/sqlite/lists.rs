@@ -0,0 +1,88 @@
+use axum::async_trait;
+use sqlx::SqlitePool;
+
+use crate::{lists::TodoList, members::Role, repository::ListRepository};
+
+pub struct SqliteListRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteListRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl ListRepository for SqliteListRepository {
+    async fn list(&self, user_id: i64) -> anyhow::Result<Vec<TodoList>> {
+        let lists = sqlx::query_as(
+            "SELECT lists.id, lists.name, list_members.role FROM lists \
+             JOIN list_members ON list_members.list_id = lists.id \
+             WHERE list_members.user_id = ? ORDER BY lists.id",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(lists)
+    }
+
+    async fn get(&self, user_id: i64, id: i64) -> anyhow::Result<Option<TodoList>> {
+        let list = sqlx::query_as(
+            "SELECT lists.id, lists.name, list_members.role FROM lists \
+             JOIN list_members ON list_members.list_id = lists.id \
+             WHERE list_members.user_id = ? AND lists.id = ?",
+        )
+        .bind(user_id)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(list)
+    }
+
+    async fn create(&self, user_id: i64, name: &str) -> anyhow::Result<TodoList> {
+        let mut tx = self.pool.begin().await?;
+        let (id,): (i64,) =
+            sqlx::query_as("INSERT INTO lists (user_id, name) VALUES (?, ?) RETURNING id")
+                .bind(user_id)
+                .bind(name)
+                .fetch_one(&mut *tx)
+                .await?;
+        sqlx::query("INSERT INTO list_members (list_id, user_id, role) VALUES (?, ?, ?)")
+            .bind(id)
+            .bind(user_id)
+            .bind(Role::Owner)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(TodoList {
+            id,
+            name: name.to_owned(),
+            role: Role::Owner,
+        })
+    }
+
+    async fn rename(&self, user_id: i64, id: i64, name: &str) -> anyhow::Result<Option<TodoList>> {
+        sqlx::query(
+            "UPDATE lists SET name = ? WHERE id = ? \
+             AND id IN (SELECT list_id FROM list_members WHERE user_id = ?)",
+        )
+        .bind(name)
+        .bind(id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+        self.get(user_id, id).await
+    }
+
+    async fn delete(&self, user_id: i64, id: i64) -> anyhow::Result<Option<TodoList>> {
+        let Some(list) = self.get(user_id, id).await? else {
+            return Ok(None);
+        };
+        sqlx::query("DELETE FROM lists WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(Some(list))
+    }
+}
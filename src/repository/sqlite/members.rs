@@ -0,0 +1,92 @@
+use axum::async_trait;
+use sqlx::SqlitePool;
+
+use crate::{
+    members::{Invite, Member, Role},
+    repository::MemberRepository,
+};
+
+pub struct SqliteMemberRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteMemberRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl MemberRepository for SqliteMemberRepository {
+    async fn list(&self, list_id: i64) -> anyhow::Result<Vec<Member>> {
+        let members = sqlx::query_as(
+            "SELECT users.id AS user_id, users.username, list_members.role FROM list_members \
+             JOIN users ON users.id = list_members.user_id \
+             WHERE list_members.list_id = ? ORDER BY users.username",
+        )
+        .bind(list_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(members)
+    }
+
+    async fn set_role(&self, list_id: i64, user_id: i64, role: Role) -> anyhow::Result<bool> {
+        let result =
+            sqlx::query("UPDATE list_members SET role = ? WHERE list_id = ? AND user_id = ?")
+                .bind(role)
+                .bind(list_id)
+                .bind(user_id)
+                .execute(&self.pool)
+                .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn remove(&self, list_id: i64, user_id: i64) -> anyhow::Result<bool> {
+        let result = sqlx::query("DELETE FROM list_members WHERE list_id = ? AND user_id = ?")
+            .bind(list_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn create_invite(&self, invite: &Invite) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO list_invites (token, list_id, role, expires_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(&invite.token)
+        .bind(invite.list_id)
+        .bind(invite.role)
+        .bind(invite.expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn find_invite(&self, token: &str, now: i64) -> anyhow::Result<Option<Invite>> {
+        let invite = sqlx::query_as(
+            "SELECT list_invites.token, list_invites.list_id, lists.name AS list_name, \
+             list_invites.role, list_invites.expires_at FROM list_invites \
+             JOIN lists ON lists.id = list_invites.list_id \
+             WHERE list_invites.token = ? AND list_invites.expires_at > ?",
+        )
+        .bind(token)
+        .bind(now)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(invite)
+    }
+
+    async fn join(&self, list_id: i64, user_id: i64, role: Role) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO list_members (list_id, user_id, role) VALUES (?, ?, ?) \
+             ON CONFLICT (list_id, user_id) DO NOTHING",
+        )
+        .bind(list_id)
+        .bind(user_id)
+        .bind(role)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
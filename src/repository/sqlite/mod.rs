@@ -0,0 +1,38 @@
+mod lists;
+mod members;
+mod todos;
+mod users;
+
+use std::str::FromStr;
+
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    SqlitePool,
+};
+
+pub use lists::SqliteListRepository;
+pub use members::SqliteMemberRepository;
+pub use todos::SqliteTodoRepository;
+pub use users::SqliteUserRepository;
+
+/// Opens the database at `url` and brings its schema up to date.
+///
+/// In-memory databases only live as long as their connection, so the pool is kept to a single
+/// connection that is never recycled.
+pub async fn connect(url: &str) -> anyhow::Result<SqlitePool> {
+    let options = SqliteConnectOptions::from_str(url)?
+        .create_if_missing(true)
+        .foreign_keys(true);
+    let pool = if url.contains(":memory:") || url.contains("mode=memory") {
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+    } else {
+        SqlitePoolOptions::new()
+    }
+    .connect_with(options)
+    .await?;
+    sqlx::migrate!().run(&pool).await?;
+    Ok(pool)
+}
@@ -0,0 +1,853 @@
+use std::collections::BTreeSet;
+
+use axum::async_trait;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::{
+    footer::TodoCounts,
+    reminders::Reminder,
+    repository::{StaleVersion, TodoRepository},
+    todos::{Due, Priority, Progress, Todo},
+    trash::TrashedTodo,
+};
+
+const COLUMNS: &str = "id, content, done, version, due_date, due_time, time_zone, priority, tags, \
+     parent_id, position, created_at, updated_at, \
+     (SELECT COUNT(*) FROM todos AS subtask \
+      WHERE subtask.parent_id = todos.id AND subtask.deleted_at IS NULL) AS subtasks, \
+     (SELECT COUNT(*) FROM todos AS subtask \
+      WHERE subtask.parent_id = todos.id AND subtask.deleted_at IS NULL AND subtask.done) \
+     AS subtasks_done";
+
+#[derive(sqlx::FromRow)]
+struct TodoRow {
+    id: i64,
+    content: String,
+    done: bool,
+    version: i64,
+    due_date: Option<NaiveDate>,
+    due_time: Option<NaiveTime>,
+    time_zone: Option<String>,
+    priority: Priority,
+    tags: String,
+    parent_id: Option<i64>,
+    position: f64,
+    created_at: i64,
+    updated_at: i64,
+    subtasks: i64,
+    subtasks_done: i64,
+}
+
+impl TodoRow {
+    fn into_entry(self) -> (i64, Todo) {
+        let due = self.due_date.map(|date| Due {
+            date,
+            time: self.due_time,
+            time_zone: self
+                .time_zone
+                .and_then(|time_zone| time_zone.parse().ok())
+                .unwrap_or(Tz::UTC),
+        });
+        (
+            self.id,
+            Todo {
+                content: self.content,
+                done: self.done,
+                version: self.version,
+                due,
+                priority: self.priority,
+                tags: self
+                    .tags
+                    .split_whitespace()
+                    .map(ToOwned::to_owned)
+                    .collect(),
+                parent_id: self.parent_id,
+                position: self.position,
+                progress: Progress {
+                    done: self.subtasks_done,
+                    total: self.subtasks,
+                },
+                created_at: DateTime::from_timestamp(self.created_at, 0).unwrap_or_default(),
+                updated_at: DateTime::from_timestamp(self.updated_at, 0).unwrap_or_default(),
+            },
+        )
+    }
+
+    fn into_todo(self) -> Todo {
+        self.into_entry().1
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct TrashedRow {
+    #[sqlx(flatten)]
+    todo: TodoRow,
+    deleted_subtasks: i64,
+    deleted_at: i64,
+}
+
+impl TrashedRow {
+    fn into_trashed(self) -> TrashedTodo {
+        let deleted_at = DateTime::from_timestamp_millis(self.deleted_at).unwrap_or_default();
+        let (id, todo) = self.todo.into_entry();
+        TrashedTodo {
+            id,
+            todo,
+            subtasks: self.deleted_subtasks,
+            deleted_at,
+        }
+    }
+}
+
+pub struct SqliteTodoRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteTodoRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+fn join_tags(tags: &BTreeSet<String>) -> String {
+    tags.iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(sqlx::FromRow)]
+struct ReminderRow {
+    list_id: i64,
+    id: i64,
+    content: String,
+    due_at: i64,
+}
+
+/// Applies the update when `todo.version` is still the stored one, `Ok(None)` otherwise or when
+/// there is no such todo.
+async fn update_row(
+    conn: &mut SqliteConnection,
+    list_id: i64,
+    id: i64,
+    todo: Todo,
+) -> anyhow::Result<Option<TodoRow>> {
+    let due_at = todo.due_at().map(|due_at| due_at.timestamp());
+    // A new due date needs a new reminder
+    let row = sqlx::query_as(&format!(
+        "UPDATE todos SET content = ?, done = ?, version = version + 1, \
+         due_date = ?, due_time = ?, time_zone = ?, \
+         reminded = reminded AND due_at IS ?, due_at = ?, priority = ?, tags = ?, \
+         updated_at = ? \
+         WHERE list_id = ? AND id = ? AND version = ? AND deleted_at IS NULL \
+         RETURNING {COLUMNS}"
+    ))
+    .bind(todo.content)
+    .bind(todo.done)
+    .bind(todo.due.as_ref().map(|due| due.date))
+    .bind(todo.due.as_ref().and_then(|due| due.time))
+    .bind(todo.due.as_ref().map(|due| due.time_zone.name()))
+    .bind(due_at)
+    .bind(due_at)
+    .bind(todo.priority)
+    .bind(join_tags(&todo.tags))
+    .bind(Utc::now().timestamp())
+    .bind(list_id)
+    .bind(id)
+    .bind(todo.version)
+    .fetch_optional(conn)
+    .await?;
+    Ok(row)
+}
+
+async fn select_row(
+    conn: &mut SqliteConnection,
+    list_id: i64,
+    id: i64,
+) -> anyhow::Result<Option<TodoRow>> {
+    let row = sqlx::query_as(&format!(
+        "SELECT {COLUMNS} FROM todos WHERE list_id = ? AND id = ? AND deleted_at IS NULL"
+    ))
+    .bind(list_id)
+    .bind(id)
+    .fetch_optional(conn)
+    .await?;
+    Ok(row)
+}
+
+/// Completing a todo completes its subtasks, reopening a todo reopens its ancestors, as a todo
+/// cannot be done before its subtasks. Returns the ids of the other todos changed.
+async fn cascade_done(
+    conn: &mut SqliteConnection,
+    list_id: i64,
+    id: i64,
+    done: bool,
+) -> anyhow::Result<Vec<i64>> {
+    let query = if done {
+        "WITH RECURSIVE subtasks (id) AS ( \
+             SELECT id FROM todos WHERE list_id = ? AND parent_id = ? AND deleted_at IS NULL \
+             UNION ALL \
+             SELECT todos.id FROM todos JOIN subtasks ON todos.parent_id = subtasks.id \
+             WHERE todos.deleted_at IS NULL \
+         ) \
+         UPDATE todos SET done = TRUE, version = version + 1, updated_at = ? \
+         WHERE id IN (SELECT id FROM subtasks) AND NOT done \
+         RETURNING id"
+    } else {
+        "WITH RECURSIVE ancestors (id) AS ( \
+             SELECT parent_id FROM todos WHERE list_id = ? AND id = ? \
+             UNION ALL \
+             SELECT todos.parent_id FROM todos JOIN ancestors ON todos.id = ancestors.id \
+         ) \
+         UPDATE todos SET done = FALSE, version = version + 1, updated_at = ? \
+         WHERE id IN (SELECT id FROM ancestors) AND done \
+         RETURNING id"
+    };
+    let ids = sqlx::query_scalar(query)
+        .bind(list_id)
+        .bind(id)
+        .bind(Utc::now().timestamp())
+        .fetch_all(conn)
+        .await?;
+    Ok(ids)
+}
+
+/// Moves the todo and its subtasks to the trash, returns whether it was not there already. The
+/// subtasks already in the trash keep the time they were deleted at.
+async fn delete_tree(
+    conn: &mut SqliteConnection,
+    list_id: i64,
+    id: i64,
+    deleted_at: i64,
+) -> anyhow::Result<bool> {
+    let result = sqlx::query(
+        "WITH RECURSIVE deleted (id) AS ( \
+             SELECT id FROM todos WHERE list_id = ? AND id = ? AND deleted_at IS NULL \
+             UNION ALL \
+             SELECT todos.id FROM todos JOIN deleted ON todos.parent_id = deleted.id \
+             WHERE todos.deleted_at IS NULL \
+         ) \
+         UPDATE todos SET deleted_at = ? WHERE id IN (SELECT id FROM deleted)",
+    )
+    .bind(list_id)
+    .bind(id)
+    .bind(deleted_at)
+    .execute(conn)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Takes the todo out of the trash, along with the subtasks deleted with it and its ancestors.
+/// Restoring a todo not done reopens its ancestors, as adding it would. Returns whether it was in
+/// the trash.
+async fn restore_tree(conn: &mut SqliteConnection, list_id: i64, id: i64) -> anyhow::Result<bool> {
+    let deleted: Option<(i64, bool)> = sqlx::query_as(
+        "SELECT deleted_at, done FROM todos \
+         WHERE list_id = ? AND id = ? AND deleted_at IS NOT NULL",
+    )
+    .bind(list_id)
+    .bind(id)
+    .fetch_optional(&mut *conn)
+    .await?;
+    let Some((deleted_at, done)) = deleted else {
+        return Ok(false);
+    };
+    sqlx::query(
+        "WITH RECURSIVE restored (id) AS ( \
+             SELECT ? \
+             UNION ALL \
+             SELECT todos.id FROM todos JOIN restored ON todos.parent_id = restored.id \
+             WHERE todos.deleted_at = ? \
+         ), ancestors (id) AS ( \
+             SELECT parent_id FROM todos WHERE id = ? \
+             UNION ALL \
+             SELECT todos.parent_id FROM todos JOIN ancestors ON todos.id = ancestors.id \
+         ) \
+         UPDATE todos SET deleted_at = NULL \
+         WHERE id IN (SELECT id FROM restored) OR id IN (SELECT id FROM ancestors)",
+    )
+    .bind(id)
+    .bind(deleted_at)
+    .bind(id)
+    .execute(&mut *conn)
+    .await?;
+    cascade_done(conn, list_id, id, done).await?;
+    Ok(true)
+}
+
+#[async_trait]
+impl TodoRepository for SqliteTodoRepository {
+    async fn list(&self, list_id: i64) -> anyhow::Result<Vec<(i64, Todo)>> {
+        let rows: Vec<TodoRow> = sqlx::query_as(&format!(
+            "SELECT {COLUMNS} FROM todos WHERE list_id = ? AND deleted_at IS NULL \
+             ORDER BY position, id"
+        ))
+        .bind(list_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(TodoRow::into_entry).collect())
+    }
+
+    async fn counts(&self, list_id: i64) -> anyhow::Result<TodoCounts> {
+        let (total, done) = sqlx::query_as(
+            "SELECT COUNT(*), COALESCE(SUM(done), 0) FROM todos \
+             WHERE list_id = ? AND deleted_at IS NULL",
+        )
+        .bind(list_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(TodoCounts { total, done })
+    }
+
+    async fn get(&self, list_id: i64, id: i64) -> anyhow::Result<Option<Todo>> {
+        let mut conn = self.pool.acquire().await?;
+        let row = select_row(&mut conn, list_id, id).await?;
+        Ok(row.map(TodoRow::into_todo))
+    }
+
+    async fn create(&self, list_id: i64, todo: Todo) -> anyhow::Result<i64> {
+        let mut tx = self.pool.begin().await?;
+        let done = todo.done;
+        let due_at = todo.due_at().map(|due_at| due_at.timestamp());
+        let id = sqlx::query_scalar(
+            "INSERT INTO todos (list_id, content, done, due_date, due_time, time_zone, due_at, \
+             priority, tags, parent_id, created_at, updated_at, position) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ( \
+                 SELECT COALESCE(MAX(position), 0) + 1 FROM todos \
+                 WHERE list_id = ? AND parent_id IS ? \
+             )) RETURNING id",
+        )
+        .bind(list_id)
+        .bind(todo.content)
+        .bind(todo.done)
+        .bind(todo.due.as_ref().map(|due| due.date))
+        .bind(todo.due.as_ref().and_then(|due| due.time))
+        .bind(todo.due.as_ref().map(|due| due.time_zone.name()))
+        .bind(due_at)
+        .bind(todo.priority)
+        .bind(join_tags(&todo.tags))
+        .bind(todo.parent_id)
+        .bind(todo.created_at.timestamp())
+        .bind(todo.updated_at.timestamp())
+        .bind(list_id)
+        .bind(todo.parent_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        cascade_done(&mut tx, list_id, id, done).await?;
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    async fn update(
+        &self,
+        list_id: i64,
+        id: i64,
+        todo: Todo,
+    ) -> anyhow::Result<Option<(Todo, Vec<i64>)>> {
+        let mut tx = self.pool.begin().await?;
+        if let Some(row) = update_row(&mut tx, list_id, id, todo).await? {
+            let cascaded = cascade_done(&mut tx, list_id, id, row.done).await?;
+            // The progress of the todo changes along with its subtasks
+            let row = select_row(&mut tx, list_id, id).await?.unwrap_or(row);
+            tx.commit().await?;
+            return Ok(Some((row.into_todo(), cascaded)));
+        }
+        // In-memory databases have a single connection, `get` needs it back
+        drop(tx);
+
+        match self.get(list_id, id).await? {
+            Some(_) => Err(StaleVersion.into()),
+            None => Ok(None),
+        }
+    }
+
+    async fn update_many(&self, list_id: i64, todos: Vec<(i64, Todo)>) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let mut updated = Vec::new();
+        for (id, todo) in todos {
+            let done = todo.done;
+            // Dropping the transaction rolls back the todos already updated
+            if update_row(&mut tx, list_id, id, todo).await?.is_none() {
+                return Err(StaleVersion.into());
+            }
+            updated.push((id, done));
+        }
+        // Once all updated, not to change the version of those still to update
+        for (id, done) in updated {
+            cascade_done(&mut tx, list_id, id, done).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn delete(&self, list_id: i64, id: i64) -> anyhow::Result<Option<Todo>> {
+        let mut tx = self.pool.begin().await?;
+        let row: Option<TodoRow> = sqlx::query_as(&format!(
+            "SELECT {COLUMNS} FROM todos WHERE list_id = ? AND id = ? AND deleted_at IS NULL"
+        ))
+        .bind(list_id)
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        delete_tree(&mut tx, list_id, id, Utc::now().timestamp_millis()).await?;
+        tx.commit().await?;
+        Ok(Some(row.into_todo()))
+    }
+
+    async fn delete_many(&self, list_id: i64, ids: &[i64]) -> anyhow::Result<Vec<i64>> {
+        let mut tx = self.pool.begin().await?;
+        let deleted_at = Utc::now().timestamp_millis();
+        let mut deleted = Vec::new();
+        for id in ids {
+            if delete_tree(&mut tx, list_id, *id, deleted_at).await? {
+                deleted.push(*id);
+            }
+        }
+        tx.commit().await?;
+        Ok(deleted)
+    }
+
+    async fn trash(&self, list_id: i64) -> anyhow::Result<Vec<TrashedTodo>> {
+        let rows: Vec<TrashedRow> = sqlx::query_as(&format!(
+            "SELECT {COLUMNS}, deleted_at, \
+             (SELECT COUNT(*) FROM todos AS subtask \
+              WHERE subtask.parent_id = todos.id AND subtask.deleted_at = todos.deleted_at) \
+             AS deleted_subtasks \
+             FROM todos \
+             WHERE list_id = ? AND deleted_at IS NOT NULL AND NOT EXISTS ( \
+                 SELECT 1 FROM todos AS parent \
+                 WHERE parent.id = todos.parent_id AND parent.deleted_at = todos.deleted_at \
+             ) \
+             ORDER BY deleted_at DESC, id"
+        ))
+        .bind(list_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(TrashedRow::into_trashed).collect())
+    }
+
+    async fn restore(&self, list_id: i64, id: i64) -> anyhow::Result<Option<Todo>> {
+        let mut tx = self.pool.begin().await?;
+        if !restore_tree(&mut tx, list_id, id).await? {
+            return Ok(None);
+        }
+        tx.commit().await?;
+        self.get(list_id, id).await
+    }
+
+    async fn restore_many(&self, list_id: i64, ids: &[i64]) -> anyhow::Result<Vec<i64>> {
+        let mut tx = self.pool.begin().await?;
+        let mut restored = Vec::new();
+        for id in ids {
+            if restore_tree(&mut tx, list_id, *id).await? {
+                restored.push(*id);
+            }
+        }
+        tx.commit().await?;
+        Ok(restored)
+    }
+
+    async fn move_to_list(
+        &self,
+        list_id: i64,
+        ids: &[i64],
+        to_list_id: i64,
+    ) -> anyhow::Result<Vec<i64>> {
+        let mut tx = self.pool.begin().await?;
+        let mut moved = Vec::new();
+        for id in ids {
+            // Last among the top-level todos of the other list, the subtasks follow
+            let result = sqlx::query(
+                "UPDATE todos SET list_id = ?, parent_id = NULL, version = version + 1, \
+                 updated_at = ?, position = ( \
+                     SELECT COALESCE(MAX(position), 0) + 1 FROM todos \
+                     WHERE list_id = ? AND parent_id IS NULL \
+                 ) \
+                 WHERE list_id = ? AND id = ? AND deleted_at IS NULL",
+            )
+            .bind(to_list_id)
+            .bind(Utc::now().timestamp())
+            .bind(to_list_id)
+            .bind(list_id)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+            if result.rows_affected() == 0 {
+                continue;
+            }
+            sqlx::query(
+                "WITH RECURSIVE subtasks (id) AS ( \
+                     SELECT id FROM todos WHERE parent_id = ? \
+                     UNION ALL \
+                     SELECT todos.id FROM todos JOIN subtasks ON todos.parent_id = subtasks.id \
+                 ) \
+                 UPDATE todos SET list_id = ? WHERE id IN (SELECT id FROM subtasks)",
+            )
+            .bind(id)
+            .bind(to_list_id)
+            .execute(&mut *tx)
+            .await?;
+            moved.push(*id);
+        }
+        tx.commit().await?;
+        Ok(moved)
+    }
+
+    async fn purge(&self, before: i64) -> anyhow::Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        // Without the subtasks deleted along with their parent
+        let purged: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM todos \
+             WHERE deleted_at < ? AND NOT EXISTS ( \
+                 SELECT 1 FROM todos AS parent \
+                 WHERE parent.id = todos.parent_id AND parent.deleted_at = todos.deleted_at \
+             )",
+        )
+        .bind(before * 1000)
+        .fetch_one(&mut *tx)
+        .await?;
+        // Their subtasks, all deleted with or before them, go along
+        sqlx::query("DELETE FROM todos WHERE deleted_at < ?")
+            .bind(before * 1000)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(purged as u64)
+    }
+
+    async fn move_after(&self, list_id: i64, id: i64, after: Option<i64>) -> anyhow::Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        let parent_id: Option<Option<i64>> = sqlx::query_scalar(
+            "SELECT parent_id FROM todos WHERE list_id = ? AND id = ? AND deleted_at IS NULL",
+        )
+        .bind(list_id)
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(parent_id) = parent_id else {
+            return Ok(false);
+        };
+        let mut siblings: Vec<(i64, f64)> = sqlx::query_as(
+            "SELECT id, position FROM todos \
+             WHERE list_id = ? AND parent_id IS ? AND id != ? AND deleted_at IS NULL \
+             ORDER BY position, id",
+        )
+        .bind(list_id)
+        .bind(parent_id)
+        .bind(id)
+        .fetch_all(&mut *tx)
+        .await?;
+        let index = match after {
+            None => 0,
+            Some(after) => match siblings.iter().position(|(id, _)| *id == after) {
+                Some(index) => index + 1,
+                None => return Ok(false),
+            },
+        };
+
+        let before = index.checked_sub(1).map(|index| siblings[index].1);
+        let next = siblings.get(index).map(|(_, position)| *position);
+        let position = match (before, next) {
+            (None, None) => Some(1.0),
+            (Some(before), None) => Some(before + 1.0),
+            (None, Some(next)) => Some(next - 1.0),
+            // Gaps halve with every move between the same todos, until positions run out of
+            // precision
+            (Some(before), Some(next)) => Some(before + (next - before) / 2.0)
+                .filter(|middle| before < *middle && *middle < next),
+        };
+        match position {
+            Some(position) => {
+                sqlx::query("UPDATE todos SET position = ? WHERE id = ?")
+                    .bind(position)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            None => {
+                siblings.insert(index, (id, 0.0));
+                for (index, (id, _)) in siblings.into_iter().enumerate() {
+                    sqlx::query("UPDATE todos SET position = ? WHERE id = ?")
+                        .bind(index as f64 + 1.0)
+                        .bind(id)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+            }
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn take_reminders(&self, now: i64) -> anyhow::Result<Vec<Reminder>> {
+        let rows: Vec<ReminderRow> = sqlx::query_as(
+            "UPDATE todos SET reminded = TRUE \
+             WHERE due_at <= ? AND NOT reminded AND NOT done AND deleted_at IS NULL \
+             RETURNING list_id, id, content, due_at",
+        )
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| Reminder {
+                list_id: row.list_id,
+                todo_id: row.id,
+                content: row.content,
+                due_at: DateTime::from_timestamp(row.due_at, 0).unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    async fn adopt(&self, owner: &str, list_id: i64) -> anyhow::Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        // Last among the top-level todos of the list, in the order they were created
+        let now = Utc::now().timestamp();
+        let adopted = sqlx::query(
+            "INSERT INTO todos (list_id, content, done, version, created_at, updated_at, position) \
+             SELECT ?, content, done, version, ?, ?, ( \
+                 SELECT COALESCE(MAX(position), 0) FROM todos \
+                 WHERE list_id = ? AND parent_id IS NULL \
+             ) + ROW_NUMBER() OVER (ORDER BY id) \
+             FROM anonymous_todos WHERE owner = ? ORDER BY id",
+        )
+        .bind(list_id)
+        .bind(now)
+        .bind(now)
+        .bind(list_id)
+        .bind(owner)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        sqlx::query("DELETE FROM anonymous_todos WHERE owner = ?")
+            .bind(owner)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(adopted)
+    }
+
+    async fn anonymous_owners(&self) -> anyhow::Result<Vec<String>> {
+        let owners = sqlx::query_scalar("SELECT DISTINCT owner FROM anonymous_todos")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(owners)
+    }
+
+    async fn drop_anonymous(&self, owner: &str) -> anyhow::Result<u64> {
+        Ok(sqlx::query("DELETE FROM anonymous_todos WHERE owner = ?")
+            .bind(owner)
+            .execute(&self.pool)
+            .await?
+            .rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use sqlx::SqlitePool;
+
+    use super::SqliteTodoRepository;
+    use crate::{
+        repository::{
+            connect, ListRepository, SqliteListRepository, SqliteUserRepository, TodoRepository,
+            UserRepository,
+        },
+        todos::Todo,
+    };
+
+    /// A repository over a fresh database, and a list holding the todos `contents`.
+    async fn repository(contents: &[&str]) -> (SqlitePool, SqliteTodoRepository, i64, Vec<i64>) {
+        let pool = connect("sqlite::memory:").await.unwrap();
+        let users = SqliteUserRepository::new(pool.clone());
+        let user = users.create("alice", "hash").await.unwrap().unwrap();
+        let lists = SqliteListRepository::new(pool.clone());
+        let list = lists.create(user.id, "Todo").await.unwrap();
+        let todos = SqliteTodoRepository::new(pool.clone());
+        let mut ids = Vec::new();
+        for content in contents {
+            let todo = Todo::new(content.to_string());
+            ids.push(todos.create(list.id, todo).await.unwrap());
+        }
+        (pool, todos, list.id, ids)
+    }
+
+    /// The contents of the top-level todos of the list, in order, with their position.
+    async fn positions(todos: &SqliteTodoRepository, list_id: i64) -> Vec<(String, f64)> {
+        let todos = todos.list(list_id).await.unwrap();
+        todos
+            .into_iter()
+            .filter(|(_, todo)| todo.parent_id.is_none())
+            .map(|(_, todo)| (todo.content, todo.position))
+            .collect()
+    }
+
+    fn todos(positions: &[(&str, f64)]) -> Vec<(String, f64)> {
+        positions
+            .iter()
+            .map(|(content, position)| (content.to_string(), *position))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn move_after_takes_the_middle_of_the_neighbours() {
+        let (_, repository, list_id, ids) = repository(&["a", "b", "c"]).await;
+        assert!(repository
+            .move_after(list_id, ids[2], Some(ids[0]))
+            .await
+            .unwrap());
+        assert_eq!(
+            positions(&repository, list_id).await,
+            todos(&[("a", 1.0), ("c", 1.5), ("b", 2.0)])
+        );
+
+        assert!(repository.move_after(list_id, ids[1], None).await.unwrap());
+        assert!(repository
+            .move_after(list_id, ids[0], Some(ids[2]))
+            .await
+            .unwrap());
+        assert_eq!(
+            positions(&repository, list_id).await,
+            todos(&[("b", 0.0), ("c", 1.5), ("a", 2.5)])
+        );
+    }
+
+    #[tokio::test]
+    async fn move_after_renumbers_the_siblings_once_positions_run_out() {
+        let (pool, repository, list_id, ids) = repository(&["a", "b", "c"]).await;
+        sqlx::query("UPDATE todos SET position = ? WHERE id = ?")
+            .bind(1.0 + f64::EPSILON)
+            .bind(ids[1])
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert!(repository
+            .move_after(list_id, ids[2], Some(ids[0]))
+            .await
+            .unwrap());
+        assert_eq!(
+            positions(&repository, list_id).await,
+            todos(&[("a", 1.0), ("c", 2.0), ("b", 3.0)])
+        );
+    }
+
+    #[tokio::test]
+    async fn move_after_only_moves_after_a_sibling() {
+        let (_, repository, list_id, ids) = repository(&["a", "b"]).await;
+        let subtask = Todo {
+            parent_id: Some(ids[0]),
+            ..Todo::new("a.1".to_owned())
+        };
+        let subtask_id = repository.create(list_id, subtask).await.unwrap();
+
+        assert!(!repository
+            .move_after(list_id, ids[1], Some(subtask_id))
+            .await
+            .unwrap());
+        assert!(!repository
+            .move_after(list_id, ids[1], Some(404))
+            .await
+            .unwrap());
+        assert!(!repository.move_after(list_id, 404, None).await.unwrap());
+        assert_eq!(
+            positions(&repository, list_id).await,
+            todos(&[("a", 1.0), ("b", 2.0)])
+        );
+    }
+
+    /// The contents of every todo of the list, subtasks included, in order.
+    async fn contents(todos: &SqliteTodoRepository, list_id: i64) -> Vec<String> {
+        let todos = todos.list(list_id).await.unwrap();
+        todos.into_iter().map(|(_, todo)| todo.content).collect()
+    }
+
+    #[tokio::test]
+    async fn purge_counts_the_todos_without_the_subtasks_deleted_along() {
+        let (_, repository, list_id, ids) = repository(&["a", "b", "c"]).await;
+        let subtask = Todo {
+            parent_id: Some(ids[0]),
+            ..Todo::new("a.1".to_owned())
+        };
+        repository.create(list_id, subtask).await.unwrap();
+        repository.delete(list_id, ids[0]).await.unwrap();
+        repository.delete(list_id, ids[1]).await.unwrap();
+
+        let now = Utc::now().timestamp();
+        assert_eq!(repository.purge(now - 60).await.unwrap(), 0);
+        assert_eq!(repository.trash(list_id).await.unwrap().len(), 2);
+        assert_eq!(repository.purge(now + 1).await.unwrap(), 2);
+        assert!(repository.trash(list_id).await.unwrap().is_empty());
+        assert_eq!(contents(&repository, list_id).await, ["c"]);
+    }
+
+    #[tokio::test]
+    async fn restore_brings_back_the_subtasks_and_the_ancestors() {
+        let (_, repository, list_id, ids) = repository(&["a", "b"]).await;
+        let subtask = Todo {
+            parent_id: Some(ids[0]),
+            ..Todo::new("a.1".to_owned())
+        };
+        let subtask_id = repository.create(list_id, subtask).await.unwrap();
+
+        repository.delete(list_id, ids[0]).await.unwrap();
+        assert_eq!(contents(&repository, list_id).await, ["b"]);
+        let restored = repository.restore(list_id, ids[0]).await.unwrap();
+        assert_eq!(restored.map(|todo| todo.content).as_deref(), Some("a"));
+        assert_eq!(contents(&repository, list_id).await, ["a", "a.1", "b"]);
+
+        repository.delete(list_id, ids[0]).await.unwrap();
+        let restored = repository.restore(list_id, subtask_id).await.unwrap();
+        assert_eq!(restored.map(|todo| todo.content).as_deref(), Some("a.1"));
+        assert_eq!(contents(&repository, list_id).await, ["a", "a.1", "b"]);
+        assert!(repository.trash(list_id).await.unwrap().is_empty());
+
+        assert!(repository.restore(list_id, ids[1]).await.unwrap().is_none());
+        assert!(repository.restore(list_id, 404).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn updating_the_done_state_cascades() {
+        let (_, repository, list_id, ids) = repository(&["a"]).await;
+        let subtask = Todo {
+            parent_id: Some(ids[0]),
+            ..Todo::new("a.1".to_owned())
+        };
+        let subtask_id = repository.create(list_id, subtask).await.unwrap();
+
+        let mut todo = repository.get(list_id, ids[0]).await.unwrap().unwrap();
+        todo.done = true;
+        let (todo, cascaded) = repository
+            .update(list_id, ids[0], todo)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(cascaded, [subtask_id]);
+        assert_eq!((todo.progress.done, todo.progress.total), (1, 1));
+
+        let subtask = Todo {
+            parent_id: Some(subtask_id),
+            ..Todo::new("a.1.1".to_owned())
+        };
+        repository.create(list_id, subtask).await.unwrap();
+        let todos = repository.list(list_id).await.unwrap();
+        assert!(todos.iter().all(|(_, todo)| !todo.done));
+
+        let done = todos
+            .into_iter()
+            .map(|(id, mut todo)| {
+                todo.done = true;
+                (id, todo)
+            })
+            .collect();
+        repository.update_many(list_id, done).await.unwrap();
+        let todos = repository.list(list_id).await.unwrap();
+        assert!(todos.iter().all(|(_, todo)| todo.done));
+    }
+}
@@ -0,0 +1,39 @@
+use axum::async_trait;
+use sqlx::SqlitePool;
+
+use crate::{auth::User, repository::UserRepository};
+
+pub struct SqliteUserRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteUserRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl UserRepository for SqliteUserRepository {
+    async fn create(&self, username: &str, password_hash: &str) -> anyhow::Result<Option<User>> {
+        let user = sqlx::query_as(
+            "INSERT INTO users (username, password_hash) VALUES (?, ?) \
+             ON CONFLICT (username) DO NOTHING \
+             RETURNING id, username, password_hash",
+        )
+        .bind(username)
+        .bind(password_hash)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(user)
+    }
+
+    async fn find_by_username(&self, username: &str) -> anyhow::Result<Option<User>> {
+        let user =
+            sqlx::query_as("SELECT id, username, password_hash FROM users WHERE username = ?")
+                .bind(username)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(user)
+    }
+}
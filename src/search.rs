@@ -0,0 +1,225 @@
+//! Search of the todos of a list: the words searched are matched against the content and the tags
+//! of each todo, regardless of their case and accents, and the todos matching all of them are
+//! ranked by how well they do.
+
+use leptos::*;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::todos::Todo;
+
+/// Folds a text for matching: lowercase, without accents.
+pub fn fold(text: &str) -> String {
+    text.chars().flat_map(fold_char).collect()
+}
+
+fn fold_char(c: char) -> impl Iterator<Item = char> {
+    c.to_lowercase().nfd().filter(|c| !is_combining_mark(*c))
+}
+
+/// Words searched, folded.
+#[derive(Debug, Clone, Default)]
+pub struct Terms(Vec<String>);
+
+impl Terms {
+    pub fn parse(search: &str) -> Self {
+        let mut terms: Vec<String> = fold(search)
+            .split_whitespace()
+            .map(ToOwned::to_owned)
+            .collect();
+        terms.sort_unstable();
+        terms.dedup();
+        Self(terms)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Relevance of the todo, higher is better. `None` when one of the terms matches neither its
+    /// content nor its tags.
+    ///
+    /// Each term counts for its best match: a whole word or tag first, then the start of a word,
+    /// then part of a tag, then anywhere in the content.
+    pub fn score(&self, todo: &Todo) -> Option<u32> {
+        let content = fold(&todo.content);
+        let words: Vec<&str> = content
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect();
+        let tags: Vec<String> = todo.tags.iter().map(|tag| fold(tag)).collect();
+
+        self.0.iter().try_fold(0, |score, term| {
+            let term = term.as_str();
+            let best = [
+                (words.contains(&term) || tags.iter().any(|tag| tag == term)).then_some(4),
+                words.iter().any(|word| word.starts_with(term)).then_some(3),
+                tags.iter().any(|tag| tag.contains(term)).then_some(2),
+                content.contains(term).then_some(1),
+            ]
+            .into_iter()
+            .flatten()
+            .max()?;
+            Some(score + best)
+        })
+    }
+
+    /// Splits the text in the parts matching one of the terms and the others, in order.
+    pub fn highlight(&self, text: &str) -> Vec<(String, bool)> {
+        // Which char of the text each byte of the folded text comes from
+        let chars: Vec<char> = text.chars().collect();
+        let mut folded = String::new();
+        let mut origins = Vec::new();
+        // Chars folded away, combining marks, which go along with the char before them
+        let mut marks = Vec::new();
+        for (index, c) in chars.iter().enumerate() {
+            let len = folded.len();
+            folded.extend(fold_char(*c));
+            if folded.len() == len {
+                marks.push(index);
+            }
+            origins.resize(folded.len(), index);
+        }
+
+        let mut matched = vec![false; chars.len()];
+        for term in &self.0 {
+            for (start, _) in folded.match_indices(term.as_str()) {
+                for index in &origins[start..start + term.len()] {
+                    matched[*index] = true;
+                }
+            }
+        }
+        for index in marks {
+            matched[index] = index > 0 && matched[index - 1];
+        }
+
+        let mut parts: Vec<(String, bool)> = Vec::new();
+        for (c, matched) in chars.into_iter().zip(matched) {
+            match parts.last_mut() {
+                Some((part, part_matched)) if *part_matched == matched => part.push(c),
+                _ => parts.push((c.to_string(), matched)),
+            }
+        }
+        parts
+    }
+}
+
+/// Text with the parts matching the terms searched highlighted.
+#[component]
+pub fn Highlighted(text: String, #[prop(optional)] terms: Terms) -> impl IntoView {
+    if terms.is_empty() {
+        return text.into_view();
+    }
+    terms
+        .highlight(&text)
+        .into_iter()
+        .map(|(part, matched)| {
+            if matched {
+                view! { <mark class="bg-yellow-200">{part}</mark> }.into_view()
+            } else {
+                part.into_view()
+            }
+        })
+        .collect_view()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::Terms;
+    use crate::todos::{GetTodosForm, Todo};
+
+    fn todo(content: &str, tags: &[&str]) -> Todo {
+        let mut todo = Todo::new(content.to_owned());
+        todo.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        todo
+    }
+
+    fn highlight(search: &str, text: &str) -> Vec<(String, bool)> {
+        Terms::parse(search).highlight(text)
+    }
+
+    fn part(text: &str, matched: bool) -> (String, bool) {
+        (text.to_owned(), matched)
+    }
+
+    #[test]
+    fn accents_and_case_are_ignored() {
+        let cafe = todo("Un Café noir", &[]);
+        assert_eq!(Terms::parse("cafe").score(&cafe), Some(4));
+        assert_eq!(Terms::parse("CAFÉ").score(&cafe), Some(4));
+        assert_eq!(
+            highlight("cafe", "Un Café noir"),
+            [part("Un ", false), part("Café", true), part(" noir", false)]
+        );
+        assert_eq!(highlight("ÉTÉ", "Été"), [part("Été", true)]);
+    }
+
+    #[test]
+    fn combining_marks_stay_with_their_char() {
+        // "é" as "e" followed by a combining acute accent
+        let text = "Cafe\u{301} noir";
+        assert_eq!(Terms::parse("café").score(&todo(text, &[])), Some(4));
+        assert_eq!(
+            highlight("cafe", text),
+            [part("Cafe\u{301}", true), part(" noir", false)]
+        );
+        assert_eq!(
+            highlight("noir", text),
+            [part("Cafe\u{301} ", false), part("noir", true)]
+        );
+    }
+
+    #[test]
+    fn repeated_terms_count_once() {
+        let milk = todo("Milk, more milk", &[]);
+        assert_eq!(
+            Terms::parse("milk milk MILK").score(&milk),
+            Terms::parse("milk").score(&milk)
+        );
+        assert_eq!(
+            highlight("milk milk", "Milk, more milk"),
+            [
+                part("Milk", true),
+                part(", more ", false),
+                part("milk", true)
+            ]
+        );
+        // Overlapping terms highlight their union
+        assert_eq!(
+            highlight("mil ilk", "Buy milk"),
+            [part("Buy ", false), part("milk", true)]
+        );
+    }
+
+    #[test]
+    fn every_term_must_match() {
+        let terms = Terms::parse("milk eggs");
+        assert_eq!(terms.score(&todo("Buy milk", &[])), None);
+        assert_eq!(terms.score(&todo("Buy milk", &["eggs"])), Some(8));
+    }
+
+    #[test]
+    fn best_matches_rank_first() {
+        let todos = vec![
+            (1, todo("Buttermilk pancakes", &[])),
+            (2, todo("Eggs", &[])),
+            (3, todo("Shopping", &["milky"])),
+            (4, todo("Milkshake", &[])),
+            (5, todo("Buy milk", &[])),
+            (6, todo("Groceries", &["milk"])),
+        ];
+        let form = GetTodosForm {
+            q: "milk".to_owned(),
+            ..GetTodosForm::default()
+        };
+        let ranked: Vec<i64> = form
+            .apply(todos, Utc::now())
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        // Whole words and tags, in the order of the list, then the start of a word, part of a tag
+        // and anywhere in the content
+        assert_eq!(ranked, [5, 6, 4, 3, 1]);
+    }
+}
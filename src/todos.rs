@@ -0,0 +1,1918 @@
+//! The todos of a list, rendered as rows of the list page.
+
+use std::{
+    cmp::Ordering,
+    collections::{BTreeSet, HashMap, HashSet},
+};
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode, Uri},
+    response::{Html, IntoResponse, Response},
+};
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use leptos::*;
+use serde::{
+    de::{self, DeserializeOwned, IntoDeserializer},
+    Deserialize, Deserializer, Serialize,
+};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    errors::ApplicationError,
+    events::{ClientId, TodoChange},
+    extract::{Form, Path, Query},
+    footer::TodoFooter,
+    lists::CurrentList,
+    members::Role,
+    presence::EditorBadge,
+    search::{Highlighted, Terms},
+    trash::UndoToast,
+    validation, AppState,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Todo {
+    pub content: String,
+    pub done: bool,
+    /// Bumped on every update, so concurrent changes can be detected.
+    pub version: i64,
+    pub due: Option<Due>,
+    pub priority: Priority,
+    /// Lowercase, without spaces.
+    pub tags: BTreeSet<String>,
+    /// Id of the todo this one is a subtask of.
+    pub parent_id: Option<i64>,
+    /// Among its siblings, in the order they were dragged in. Set by the repository.
+    #[serde(skip)]
+    pub position: f64,
+    /// Of the direct subtasks, computed when loading the todo.
+    #[serde(default)]
+    pub progress: Progress,
+    /// Set by the repository.
+    pub created_at: DateTime<Utc>,
+    /// Set by the repository on every update.
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Todo {
+    pub fn new(content: String) -> Self {
+        Self {
+            content,
+            done: false,
+            version: 0,
+            due: None,
+            priority: Priority::Normal,
+            tags: BTreeSet::new(),
+            parent_id: None,
+            position: 0.0,
+            progress: Progress::default(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    pub fn due_at(&self) -> Option<DateTime<Utc>> {
+        self.due.as_ref().map(Due::at)
+    }
+
+    pub fn is_overdue(&self, now: DateTime<Utc>) -> bool {
+        !self.done && self.due_at().is_some_and(|due_at| due_at <= now)
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    sqlx::Type,
+    ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl Priority {
+    const ALL: [Priority; 3] = [Priority::High, Priority::Normal, Priority::Low];
+
+    /// Value of the priority in forms.
+    fn value(self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Priority::Low => "Low",
+            Priority::Normal => "Normal",
+            Priority::High => "High",
+        }
+    }
+}
+
+/// How many subtasks of a todo are done.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema)]
+pub struct Progress {
+    pub done: i64,
+    pub total: i64,
+}
+
+/// When a todo is due: a day, optionally a time of that day, in a time zone.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Due {
+    #[schema(value_type = String, format = Date, example = "2024-05-01")]
+    pub date: NaiveDate,
+    /// The todo is due at the end of the day when missing.
+    #[schema(value_type = Option<String>, example = "14:30:00")]
+    pub time: Option<NaiveTime>,
+    /// IANA name of the time zone.
+    #[schema(value_type = String, example = "Europe/Paris")]
+    pub time_zone: Tz,
+}
+
+impl Due {
+    pub fn at(&self) -> DateTime<Utc> {
+        let time = self
+            .time
+            .unwrap_or_else(|| NaiveTime::from_hms_opt(23, 59, 59).unwrap());
+        let local = self.date.and_time(time);
+        // Times skipped by a DST change are moved to the end of the gap, rather than rejected.
+        // Changes happen on whole minutes, and move clocks by a few hours at most.
+        let minute = local.with_second(0).unwrap_or(local);
+        let due = self
+            .time_zone
+            .from_local_datetime(&local)
+            .earliest()
+            .or_else(|| {
+                (1..=24 * 60).find_map(|minutes| {
+                    let later = minute + Duration::minutes(minutes);
+                    self.time_zone.from_local_datetime(&later).earliest()
+                })
+            });
+        due.map_or_else(|| local.and_utc(), |due| due.with_timezone(&Utc))
+    }
+}
+
+/// Filters on the todos, combined, and the order to sort the matching ones in.
+#[derive(Clone, Default, Deserialize, IntoParams, ToSchema)]
+#[into_params(parameter_in = Query)]
+pub struct GetTodosForm {
+    #[serde(default)]
+    pub filter: Filter,
+    #[serde(default)]
+    pub due: DueFilter,
+    /// Any priority when missing or empty.
+    #[serde(default, deserialize_with = "blank_as_none")]
+    pub priority: Option<Priority>,
+    /// Any tags when missing or empty.
+    #[serde(default, deserialize_with = "blank_as_none")]
+    pub tag: Option<String>,
+    /// Words to search in the content and tags of the todos. The todos matching all of them are
+    /// ranked by relevance, then by `order`.
+    #[serde(default)]
+    pub q: String,
+    #[serde(default)]
+    pub order: Order,
+}
+
+/// Reads an empty parameter, which forms send for the "any" option of a select, as a missing one.
+fn blank_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(value) if !value.trim().is_empty() => {
+            T::deserialize(value.trim().into_deserializer()).map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Default, ToSchema)]
+pub enum Filter {
+    #[default]
+    All,
+    Done,
+    NotDone,
+}
+
+impl Filter {
+    pub const ALL: [Filter; 3] = [Filter::All, Filter::Done, Filter::NotDone];
+
+    /// Value of the filter in forms.
+    pub fn value(self) -> &'static str {
+        match self {
+            Filter::All => "All",
+            Filter::Done => "Done",
+            Filter::NotDone => "NotDone",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Filter::All => "All",
+            Filter::Done => "Done",
+            Filter::NotDone => "Not done",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Default, ToSchema)]
+pub enum DueFilter {
+    #[default]
+    Any,
+    Overdue,
+    Upcoming,
+    NoDueDate,
+}
+
+impl DueFilter {
+    const ALL: [DueFilter; 4] = [
+        DueFilter::Any,
+        DueFilter::Overdue,
+        DueFilter::Upcoming,
+        DueFilter::NoDueDate,
+    ];
+
+    /// Value of the filter in forms.
+    fn value(self) -> &'static str {
+        match self {
+            DueFilter::Any => "Any",
+            DueFilter::Overdue => "Overdue",
+            DueFilter::Upcoming => "Upcoming",
+            DueFilter::NoDueDate => "NoDueDate",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            DueFilter::Any => "Any due date",
+            DueFilter::Overdue => "Overdue",
+            DueFilter::Upcoming => "Upcoming",
+            DueFilter::NoDueDate => "No due date",
+        }
+    }
+}
+
+/// Subtasks are sorted among their siblings.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Default, ToSchema)]
+pub enum Order {
+    /// The order the todos were dragged in.
+    #[default]
+    Position,
+    /// Oldest first.
+    Created,
+    /// Most recently updated first.
+    Updated,
+    /// By content, regardless of case.
+    Alphabetical,
+    /// Soonest due first, then the todos without a due date.
+    DueDate,
+    /// Highest priority first.
+    Priority,
+    /// The todos not done first, in the order they were dragged in.
+    DoneLast,
+}
+
+impl Order {
+    const ALL: [Order; 7] = [
+        Order::Position,
+        Order::Created,
+        Order::Updated,
+        Order::Alphabetical,
+        Order::DueDate,
+        Order::Priority,
+        Order::DoneLast,
+    ];
+
+    /// Value of the order in forms.
+    fn value(self) -> &'static str {
+        match self {
+            Order::Position => "Position",
+            Order::Created => "Created",
+            Order::Updated => "Updated",
+            Order::Alphabetical => "Alphabetical",
+            Order::DueDate => "DueDate",
+            Order::Priority => "Priority",
+            Order::DoneLast => "DoneLast",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Order::Position => "Custom order",
+            Order::Created => "Oldest first",
+            Order::Updated => "Recently updated",
+            Order::Alphabetical => "Alphabetical",
+            Order::DueDate => "Soonest due first",
+            Order::Priority => "Highest priority first",
+            Order::DoneLast => "Done last",
+        }
+    }
+}
+
+impl GetTodosForm {
+    /// The matching todos, in the requested order.
+    pub fn apply(&self, todos: Vec<(i64, Todo)>, now: DateTime<Utc>) -> Vec<(i64, Todo)> {
+        let terms = self.terms();
+        let mut todos: Vec<_> = todos
+            .into_iter()
+            .filter(|(_, todo)| self.matches(todo, now))
+            .filter(|(_, todo)| terms.score(todo).is_some())
+            .collect();
+        todos.sort_by_cached_key(|(id, todo)| self.cursor(&terms, *id, todo));
+        todos
+    }
+
+    /// Where the todo comes in the requested order.
+    fn cursor(&self, terms: &Terms, id: i64, todo: &Todo) -> Cursor {
+        let mut cursor = Cursor {
+            // By relevance first, the order only breaks ties
+            rank: -i64::from(terms.score(todo).unwrap_or_default()),
+            number: 0,
+            text: String::new(),
+            // The todos stay in the order they were dragged in otherwise
+            position: todo.position,
+            id,
+        };
+        match self.order {
+            Order::Position => {}
+            Order::Created => cursor.number = todo.created_at.timestamp(),
+            Order::Updated => cursor.number = -todo.updated_at.timestamp(),
+            Order::Alphabetical => cursor.text = todo.content.to_lowercase(),
+            Order::DueDate => {
+                cursor.number = todo.due_at().map_or(i64::MAX, |at| at.timestamp());
+            }
+            Order::Priority => cursor.number = -(todo.priority as i64),
+            Order::DoneLast => cursor.number = i64::from(todo.done),
+        }
+        cursor
+    }
+
+    /// Whether all the todos show, in the order they are dragged in, so they can be dragged: a todo
+    /// dropped among some of them only, or in another order, would land anywhere.
+    pub fn sortable(&self) -> bool {
+        self.order == Order::Position
+            && self.filter == Filter::All
+            && self.due == DueFilter::Any
+            && self.priority.is_none()
+            && self.tag.is_none()
+            && self.terms().is_empty()
+    }
+
+    /// The words searched, to highlight in the todos.
+    pub fn terms(&self) -> Terms {
+        Terms::parse(&self.q)
+    }
+
+    fn matches(&self, todo: &Todo, now: DateTime<Utc>) -> bool {
+        let status = match self.filter {
+            Filter::All => true,
+            Filter::Done => todo.done,
+            Filter::NotDone => !todo.done,
+        };
+        let due = match self.due {
+            DueFilter::Any => true,
+            DueFilter::Overdue => todo.is_overdue(now),
+            DueFilter::Upcoming => todo.due_at().is_some_and(|due_at| due_at > now),
+            DueFilter::NoDueDate => todo.due.is_none(),
+        };
+        let priority = self
+            .priority
+            .is_none_or(|priority| todo.priority == priority);
+        let tag = self.tag.as_ref().is_none_or(|tag| {
+            todo.tags
+                .contains(tag.trim_start_matches('#').to_lowercase().as_str())
+        });
+        status && due && priority && tag
+    }
+}
+
+/// Top-level todos per page of the list page, their subtasks come along.
+const PAGE_SIZE: usize = 50;
+
+/// Where a todo comes in the order of [`GetTodosForm::apply`], compared rather than looked up to
+/// resume after the last top-level todo of a page: that todo may have been changed, deleted or
+/// filtered out since.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cursor {
+    /// Relevance to the search, negated to rank the best matches first.
+    rank: i64,
+    /// Key of the order, when it is a number.
+    number: i64,
+    /// Key of the order, when it is text.
+    text: String,
+    position: f64,
+    id: i64,
+}
+
+impl Ord for Cursor {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.rank, self.number, &self.text)
+            .cmp(&(other.rank, other.number, &other.text))
+            .then(self.position.total_cmp(&other.position))
+            .then(self.id.cmp(&other.id))
+    }
+}
+
+impl PartialOrd for Cursor {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Cursor {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Cursor {}
+
+/// Page of the todos to load, after the first one.
+#[derive(Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageForm {
+    /// Cursor of the last top-level todo of the previous page, as JSON.
+    #[serde(default, deserialize_with = "json")]
+    #[param(value_type = Option<String>)]
+    pub after: Option<Cursor>,
+}
+
+/// Reads a parameter holding JSON.
+fn json<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(value) => serde_json::from_str(&value)
+            .map(Some)
+            .map_err(de::Error::custom),
+        None => Ok(None),
+    }
+}
+
+impl GetTodosForm {
+    /// The page of the matching todos, in the order of [`GetTodosForm::apply`], following the
+    /// cursor `after`, and the cursor of the next page when there is one. A page holds
+    /// [`PAGE_SIZE`] top-level todos, the ones whose parent is not matching, with all their
+    /// subtasks, so a [`TodoTree`] shows each page on its own.
+    pub fn page(
+        &self,
+        todos: Vec<(i64, Todo)>,
+        now: DateTime<Utc>,
+        after: Option<&Cursor>,
+    ) -> (Vec<(i64, Todo)>, Option<Cursor>) {
+        let todos = self.apply(todos, now);
+        let terms = self.terms();
+        let parents: HashMap<i64, Option<i64>> = todos
+            .iter()
+            .map(|(id, todo)| (*id, todo.parent_id))
+            .collect();
+        let root = |mut id: i64| {
+            while let Some(Some(parent_id)) = parents.get(&id) {
+                if !parents.contains_key(parent_id) {
+                    break;
+                }
+                id = *parent_id;
+            }
+            id
+        };
+
+        let roots: Vec<(i64, Cursor)> = todos
+            .iter()
+            .filter(|(id, _)| root(*id) == *id)
+            .map(|(id, todo)| (*id, self.cursor(&terms, *id, todo)))
+            .collect();
+        let start = match after {
+            Some(after) => roots.partition_point(|(_, cursor)| cursor <= after),
+            None => 0,
+        };
+        let end = roots.len().min(start + PAGE_SIZE);
+        let next = (end < roots.len()).then(|| roots[end - 1].1.clone());
+        let page_roots: HashSet<i64> = roots[start..end].iter().map(|(id, _)| *id).collect();
+
+        let todos = todos
+            .into_iter()
+            .filter(|(id, _)| page_roots.contains(&root(*id)))
+            .collect();
+        (todos, next)
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/lists/{list_id}/todos",
+    params(("list_id" = i64, Path, description = "Id of the list"), GetTodosForm, PageForm),
+    responses((status = 200, description = "A page of the matching todo rows", content_type = "text/html"))
+)]
+pub async fn get_todos(
+    State(state): State<AppState>,
+    CurrentList(list): CurrentList,
+    Query(cursor): Query<PageForm>,
+    Form(form): Form<GetTodosForm>,
+) -> Result<impl IntoResponse, ApplicationError> {
+    let (todos, next) = form.page(
+        state.todos.list(list.id).await?,
+        Utc::now(),
+        cursor.after.as_ref(),
+    );
+    let readonly = list.role < Role::Editor;
+    let editors = state.presence.editors(list.id);
+    let terms = form.terms();
+    let sortable = form.sortable();
+
+    Ok(Html(
+        leptos::ssr::render_to_string(move || {
+            view! { <TodoPage list_id=list.id todos next readonly sortable editors terms/> }
+        })
+        .into_owned(),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/lists/{list_id}/todos/{id}",
+    params(
+        ("list_id" = i64, Path, description = "Id of the list"),
+        ("id" = i64, Path, description = "Id of the todo")
+    ),
+    responses(
+        (status = 200, description = "The todo row", content_type = "text/html"),
+        (status = 404, description = "No such todo", content_type = "text/html")
+    )
+)]
+pub async fn get_todo(
+    State(state): State<AppState>,
+    CurrentList(list): CurrentList,
+    Path((_, id)): Path<(i64, i64)>,
+    ClientId(client): ClientId,
+) -> Result<impl IntoResponse, ApplicationError> {
+    let todo = state
+        .todos
+        .get(list.id, id)
+        .await?
+        .ok_or(ApplicationError::NotFound)?;
+    let readonly = list.role < Role::Editor;
+    // Getting the row back is how the editor is cancelled
+    state.presence.unlock(list.id, id, client.as_deref());
+    let editor = state.presence.editor(list.id, id);
+
+    Ok(Html(
+        leptos::ssr::render_to_string(move || {
+            view! { <Todo list_id=list.id id todo readonly editor/> }
+        })
+        .into_owned(),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/lists/{list_id}/todos/{id}/edit",
+    params(
+        ("list_id" = i64, Path, description = "Id of the list"),
+        ("id" = i64, Path, description = "Id of the todo")
+    ),
+    responses(
+        (status = 200, description = "The inline editor of the todo", content_type = "text/html"),
+        (status = 403, description = "Viewers cannot change the todos", content_type = "text/html"),
+        (status = 409, description = "Another user is editing the todo", content_type = "text/html"),
+        (status = 404, description = "No such todo", content_type = "text/html")
+    )
+)]
+pub async fn edit_todo(
+    State(state): State<AppState>,
+    CurrentList(list): CurrentList,
+    Path((_, id)): Path<(i64, i64)>,
+    ClientId(client): ClientId,
+) -> Result<impl IntoResponse, ApplicationError> {
+    list.require(Role::Editor)?;
+    let todo = state
+        .todos
+        .get(list.id, id)
+        .await?
+        .ok_or(ApplicationError::NotFound)?;
+    state
+        .presence
+        .lock(list.id, id, client.as_deref())
+        .map_err(|editor| ApplicationError::Conflict(format!("{editor} is editing this todo")))?;
+
+    Ok(Html(
+        leptos::ssr::render_to_string(move || {
+            view! { <TodoEditor list_id=list.id id form=EditTodoForm::of(&todo)/> }
+        })
+        .into_owned(),
+    ))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SetDoneForm {
+    done: bool,
+    version: i64,
+}
+
+#[utoipa::path(
+    put,
+    path = "/lists/{list_id}/todos/{id}",
+    params(
+        ("list_id" = i64, Path, description = "Id of the list"),
+        ("id" = i64, Path, description = "Id of the todo")
+    ),
+    request_body(content = SetDoneForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "The updated todo row, the rows of its subtasks or ancestors changed along and the footer follow out of band", content_type = "text/html"),
+        (status = 403, description = "Viewers cannot change the todos", content_type = "text/html"),
+        (status = 404, description = "No such todo", content_type = "text/html"),
+        (status = 409, description = "The todo changed since `version`", content_type = "text/html")
+    )
+)]
+pub async fn put_todo(
+    State(state): State<AppState>,
+    CurrentList(list): CurrentList,
+    Path((_, id)): Path<(i64, i64)>,
+    ClientId(client): ClientId,
+    Form(form): Form<SetDoneForm>,
+) -> Result<impl IntoResponse, ApplicationError> {
+    list.require(Role::Editor)?;
+    let mut todo = state
+        .todos
+        .get(list.id, id)
+        .await?
+        .ok_or(ApplicationError::NotFound)?;
+
+    // Retries and double clicks ask for the state the todo is already in
+    let mut related = Vec::new();
+    if todo.done != form.done {
+        todo.done = form.done;
+        todo.version = form.version;
+        let (updated, mut cascaded) = state
+            .todos
+            .update(list.id, id, todo)
+            .await?
+            .ok_or(ApplicationError::NotFound)?;
+        todo = updated;
+        cascaded.push(id);
+        related = changed_rows(&state, list.id, todo.parent_id, cascaded).await?;
+        if let Some(index) = related.iter().position(|(related_id, _)| *related_id == id) {
+            todo = related.remove(index).1;
+        }
+        state.events.publish(
+            list.id,
+            client.clone(),
+            TodoChange::Updated(id, todo.clone()),
+        );
+        publish_updates(&state, list.id, &client, &related);
+    }
+    let editor = state.presence.editor(list.id, id);
+    let editors = state.presence.editors(list.id);
+    let counts = state.todos.counts(list.id).await?;
+
+    Ok(Html(
+        leptos::ssr::render_to_string(move || {
+            view! {
+                <Todo list_id=list.id id todo editor/>
+                <OobRows list_id=list.id rows=related editors/>
+                <TodoFooter list_id=list.id counts oob=true/>
+            }
+        })
+        .into_owned(),
+    ))
+}
+
+/// Reloads the todos whose row changed along with another one: those of `ids` and the ancestors
+/// from `parent_id` up, whose progress changed.
+pub async fn changed_rows(
+    state: &AppState,
+    list_id: i64,
+    mut parent_id: Option<i64>,
+    mut ids: Vec<i64>,
+) -> anyhow::Result<Vec<(i64, Todo)>> {
+    let mut todos: HashMap<i64, Todo> = state.todos.list(list_id).await?.into_iter().collect();
+    while let Some(id) = parent_id {
+        ids.push(id);
+        parent_id = todos.get(&id).and_then(|todo| todo.parent_id);
+    }
+    ids.sort_unstable();
+    ids.dedup();
+    Ok(ids
+        .into_iter()
+        .filter_map(|id| Some((id, todos.remove(&id)?)))
+        .collect())
+}
+
+/// Sends the rows changed along with a todo to the other pages showing the list.
+pub fn publish_updates(
+    state: &AppState,
+    list_id: i64,
+    client: &Option<String>,
+    rows: &[(i64, Todo)],
+) {
+    for (id, todo) in rows {
+        state.events.publish(
+            list_id,
+            client.clone(),
+            TodoChange::Updated(*id, todo.clone()),
+        );
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateTodoForm {
+    pub content: String,
+    /// Id of the todo to add a subtask to.
+    #[serde(default)]
+    pub parent_id: Option<i64>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{list_id}/todos",
+    params(("list_id" = i64, Path, description = "Id of the list")),
+    request_body(content = CreateTodoForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "The new todo row", content_type = "text/html"),
+        (status = 403, description = "Viewers cannot change the todos", content_type = "text/html"),
+        (status = 404, description = "No such parent todo", content_type = "text/html"),
+        (status = 422, description = "The form with its errors", content_type = "text/html")
+    )
+)]
+pub async fn create_todo(
+    State(state): State<AppState>,
+    CurrentList(list): CurrentList,
+    ClientId(client): ClientId,
+    headers: HeaderMap,
+    Form(form): Form<CreateTodoForm>,
+) -> Result<Response, ApplicationError> {
+    list.require(Role::Editor)?;
+    let parent_id = form.parent_id;
+    let content = match validation::todo_content(&form.content) {
+        Ok(content) => content,
+        Err(error) => {
+            let form = leptos::ssr::render_to_string(move || {
+                view! { <NewTodoForm list_id=list.id parent_id value=form.content error/> }
+            });
+            return Ok(validation::invalid_form(
+                &format!("#{}", new_todo_form_id(parent_id)),
+                form.into_owned(),
+            ));
+        }
+    };
+
+    let (id, todo, related) = add_todo(&state, list.id, content, parent_id).await?;
+    state.events.publish(
+        list.id,
+        client.clone(),
+        TodoChange::Created(id, todo.clone()),
+    );
+    publish_updates(&state, list.id, &client, &related);
+    let editors = state.presence.editors(list.id);
+    let counts = state.todos.counts(list.id).await?;
+    let sortable = list.role >= Role::Editor && from_sortable_page(&headers);
+    Ok(Html(
+        leptos::ssr::render_to_string(move || {
+            view! {
+                <TodoNode list_id=list.id id todo sortable/>
+                <OobRows list_id=list.id rows=related editors/>
+                <NewTodoForm list_id=list.id parent_id oob=true/>
+                <TodoFooter list_id=list.id counts oob=true/>
+            }
+        })
+        .into_owned(),
+    )
+    .into_response())
+}
+
+/// Whether the todos of the list page the request comes from can be dragged, its filters being
+/// kept in its URL, see [`GetTodosForm::sortable`].
+fn from_sortable_page(headers: &HeaderMap) -> bool {
+    headers
+        .get("HX-Current-URL")
+        .and_then(|url| url.to_str().ok()?.parse::<Uri>().ok())
+        .and_then(|uri| axum::extract::Query::<GetTodosForm>::try_from_uri(&uri).ok())
+        .is_some_and(|axum::extract::Query(form)| form.sortable())
+}
+
+/// Creates a todo, as a subtask of `parent_id` if any. Adding a subtask reopens the ancestors of
+/// the todo, which are returned with the other rows changed.
+pub async fn add_todo(
+    state: &AppState,
+    list_id: i64,
+    content: String,
+    parent_id: Option<i64>,
+) -> Result<(i64, Todo, Vec<(i64, Todo)>), ApplicationError> {
+    if let Some(parent_id) = parent_id {
+        state
+            .todos
+            .get(list_id, parent_id)
+            .await?
+            .ok_or(ApplicationError::NotFound)?;
+    }
+
+    let mut todo = Todo::new(content);
+    todo.parent_id = parent_id;
+    let id = state.todos.create(list_id, todo).await?;
+    // As stored, with its position among its siblings
+    let todo = state
+        .todos
+        .get(list_id, id)
+        .await?
+        .ok_or(ApplicationError::NotFound)?;
+    if parent_id.is_none() {
+        return Ok((id, todo, Vec::new()));
+    }
+    // The ancestors reopened along
+    let related = changed_rows(state, list_id, parent_id, Vec::new()).await?;
+    Ok((id, todo, related))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ReorderForm {
+    /// Id of the todo dragged, nothing moved when missing.
+    id: Option<i64>,
+    /// Id of the sibling the todo was dropped after, it goes first when missing.
+    after: Option<i64>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{list_id}/todos/reorder",
+    params(("list_id" = i64, Path, description = "Id of the list")),
+    request_body(content = ReorderForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 204, description = "The todo was moved"),
+        (status = 403, description = "Viewers cannot change the todos", content_type = "text/html"),
+        (status = 404, description = "No such todo, or `after` is not a sibling of it", content_type = "text/html")
+    )
+)]
+pub async fn reorder_todos(
+    State(state): State<AppState>,
+    CurrentList(list): CurrentList,
+    ClientId(client): ClientId,
+    Form(form): Form<ReorderForm>,
+) -> Result<impl IntoResponse, ApplicationError> {
+    list.require(Role::Editor)?;
+    // Drops where they started from move nothing
+    if let Some(id) = form.id {
+        if !state.todos.move_after(list.id, id, form.after).await? {
+            return Err(ApplicationError::NotFound);
+        }
+        state.events.publish(list.id, client, TodoChange::Reload);
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct EditTodoForm {
+    content: String,
+    version: i64,
+    /// `YYYY-MM-DD`, the todo has no due date when empty.
+    #[serde(default)]
+    due_date: String,
+    /// `HH:MM`, the todo is due at the end of the day when empty.
+    #[serde(default)]
+    due_time: String,
+    /// IANA name of the time zone of the due date, UTC when empty.
+    #[serde(default)]
+    time_zone: String,
+    #[serde(default)]
+    priority: Priority,
+    /// Separated by spaces or commas.
+    #[serde(default)]
+    tags: String,
+}
+
+impl EditTodoForm {
+    /// The form filled with the todo as stored.
+    fn of(todo: &Todo) -> Self {
+        let due = todo.due.as_ref();
+        Self {
+            content: todo.content.clone(),
+            version: todo.version,
+            due_date: due
+                .map(|due| due.date.format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+            due_time: due
+                .and_then(|due| due.time)
+                .map(|time| time.format("%H:%M").to_string())
+                .unwrap_or_default(),
+            time_zone: due
+                .map(|due| due.time_zone.name().to_owned())
+                .unwrap_or_default(),
+            priority: todo.priority,
+            tags: todo
+                .tags
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/lists/{list_id}/todos/{id}",
+    params(
+        ("list_id" = i64, Path, description = "Id of the list"),
+        ("id" = i64, Path, description = "Id of the todo")
+    ),
+    request_body(content = EditTodoForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "The updated todo row", content_type = "text/html"),
+        (status = 403, description = "Viewers cannot change the todos", content_type = "text/html"),
+        (status = 404, description = "No such todo", content_type = "text/html"),
+        (status = 409, description = "The todo changed since `version`", content_type = "text/html"),
+        (status = 422, description = "The editor with its errors", content_type = "text/html")
+    )
+)]
+pub async fn patch_todo(
+    State(state): State<AppState>,
+    CurrentList(list): CurrentList,
+    Path((_, id)): Path<(i64, i64)>,
+    ClientId(client): ClientId,
+    Form(form): Form<EditTodoForm>,
+) -> Result<Response, ApplicationError> {
+    list.require(Role::Editor)?;
+    let mut todo = state
+        .todos
+        .get(list.id, id)
+        .await?
+        .ok_or(ApplicationError::NotFound)?;
+
+    let parsed = validation::todo_content(&form.content).and_then(|content| {
+        let due = validation::due(&form.due_date, &form.due_time, &form.time_zone)?;
+        let tags = validation::tags(form.tags.split(|c: char| c == ',' || c.is_whitespace()))?;
+        Ok((content, due, tags))
+    });
+    let (content, due, tags) = match parsed {
+        Ok(parsed) => parsed,
+        Err(error) => {
+            let form = leptos::ssr::render_to_string(move || {
+                view! { <TodoEditor list_id=list.id id form error/> }
+            });
+            return Ok(validation::invalid_form(
+                &format!("#todo-{id}"),
+                form.into_owned(),
+            ));
+        }
+    };
+
+    if todo.content != content
+        || todo.due != due
+        || todo.priority != form.priority
+        || todo.tags != tags
+    {
+        todo.content = content;
+        todo.due = due;
+        todo.priority = form.priority;
+        todo.tags = tags;
+        todo.version = form.version;
+        // The done state is left as is, nothing cascades
+        (todo, _) = state
+            .todos
+            .update(list.id, id, todo)
+            .await?
+            .ok_or(ApplicationError::NotFound)?;
+        state.events.publish(
+            list.id,
+            client.clone(),
+            TodoChange::Updated(id, todo.clone()),
+        );
+    }
+    state.presence.unlock(list.id, id, client.as_deref());
+
+    Ok(Html(
+        leptos::ssr::render_to_string(move || view! { <Todo list_id=list.id id todo/> })
+            .into_owned(),
+    )
+    .into_response())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/lists/{list_id}/todos/{id}",
+    params(
+        ("list_id" = i64, Path, description = "Id of the list"),
+        ("id" = i64, Path, description = "Id of the todo")
+    ),
+    responses(
+        (status = 200, description = "The todo and its subtasks were moved to the trash, the rows of its ancestors, a toast to undo it and the footer follow out of band", content_type = "text/html"),
+        (status = 403, description = "Viewers cannot change the todos", content_type = "text/html"),
+        (status = 404, description = "No such todo", content_type = "text/html")
+    )
+)]
+pub async fn delete_todo(
+    State(state): State<AppState>,
+    CurrentList(list): CurrentList,
+    Path((_, id)): Path<(i64, i64)>,
+    ClientId(client): ClientId,
+) -> Result<impl IntoResponse, ApplicationError> {
+    list.require(Role::Editor)?;
+    let todo = state
+        .todos
+        .delete(list.id, id)
+        .await?
+        .ok_or(ApplicationError::NotFound)?;
+    let related = changed_rows(&state, list.id, todo.parent_id, Vec::new()).await?;
+    state
+        .events
+        .publish(list.id, client.clone(), TodoChange::Deleted(id));
+    publish_updates(&state, list.id, &client, &related);
+    let editors = state.presence.editors(list.id);
+    let counts = state.todos.counts(list.id).await?;
+    Ok(Html(
+        leptos::ssr::render_to_string(move || {
+            view! {
+                <OobRows list_id=list.id rows=related editors/>
+                <UndoToast list_id=list.id ids=vec![id] oob=true/>
+                <TodoFooter list_id=list.id counts oob=true/>
+            }
+        })
+        .into_owned(),
+    ))
+}
+
+/// Reloads the todos of the list page, with its filters and order, once swapped out of band.
+#[component]
+pub fn ReloadTodos(list_id: i64) -> impl IntoView {
+    view! {
+        <div
+            id="reload-todos"
+            hx-swap-oob="true"
+            hx-get=format!("/lists/{list_id}/todos")
+            hx-trigger="load"
+            hx-include="#filters"
+            hx-target="#todos"
+        ></div>
+    }
+}
+
+/// Row of a todo. `readonly` rows, shown to viewers, have no way to change the todo. `editor` is
+/// the user editing the todo from another page. `oob` swaps the row out of band, in place of the
+/// one with the same id. The words searched, `terms`, are highlighted in its content and tags.
+#[component]
+pub fn Todo(
+    list_id: i64,
+    id: i64,
+    todo: Todo,
+    #[prop(optional)] readonly: bool,
+    #[prop(optional_no_strip)] editor: Option<String>,
+    #[prop(optional)] oob: bool,
+    #[prop(optional)] terms: Terms,
+) -> impl IntoView {
+    let status = if todo.done { "done" } else { "not done" };
+    let overdue = todo.is_overdue(Utc::now());
+    if readonly {
+        return view! {
+            <div id=format!("todo-{id}") class="w-full" hx-swap-oob=oob.then_some("true")>
+                <hr class="w-full"/>
+                <div class="flex flex-row justify-between w-full text-xl">
+                    <p>
+                        <Highlighted text=todo.content terms=terms.clone()/>
+                    </p>
+                    <SubtasksToggle id progress=todo.progress/>
+                    <PriorityBadge priority=todo.priority/>
+                    <TagChips tags=todo.tags terms/>
+                    <DueDate due=todo.due overdue/>
+                    <EditorBadge todo_id=id editor/>
+                    <p>{status}</p>
+                </div>
+            </div>
+        };
+    }
+
+    view! {
+        <div id=format!("todo-{id}") class="w-full" hx-swap-oob=oob.then_some("true")>
+            <hr class="w-full"/>
+            <div class="flex flex-row justify-between w-full text-xl">
+                // Selects the todo for the bulk actions, see `BulkActions`
+                <input type="checkbox" class="select-todo" value=id aria-label="Select"/>
+                <span class="drag-handle cursor-move" title="Drag to reorder">
+                    "⠿"
+                </span>
+                <p
+                    class="cursor-text"
+                    title="Click to edit"
+                    hx-get=format!("/lists/{list_id}/todos/{id}/edit")
+                    hx-target=format!("#todo-{id}")
+                    hx-swap="outerHTML"
+                >
+                    <Highlighted text=todo.content terms=terms.clone()/>
+                </p>
+                <SubtasksToggle id progress=todo.progress/>
+                <PriorityBadge priority=todo.priority/>
+                <TagChips tags=todo.tags terms/>
+                <DueDate due=todo.due overdue/>
+                <EditorBadge todo_id=id editor/>
+                <p>{status}</p>
+                <button
+                    hx-put=format!("/lists/{list_id}/todos/{id}")
+                    hx-vals=format!(r#"{{"done": {}, "version": {}}}"#, !todo.done, todo.version)
+                    hx-target=format!("#todo-{id}")
+                    hx-swap="outerHTML"
+                >
+                    {if todo.done { "Mark as not done" } else { "Mark as done" }}
+                </button>
+                <button
+                    type="button"
+                    onclick=format!(
+                        "document.getElementById('new-subtask-{id}').classList.remove('hidden')",
+                    )
+                >
+                    Add subtask
+                </button>
+                <button
+                    hx-delete=format!("/lists/{list_id}/todos/{id}")
+                    hx-target=format!("#node-{id}")
+                    hx-swap="delete"
+                    hx-confirm=(todo.progress.total > 0)
+                        .then_some("Delete this todo and its subtasks?")
+                >
+                    Delete
+                </button>
+            </div>
+        </div>
+    }
+}
+
+/// Filters and order of the todos of the list page. Changing them reloads the todos through the
+/// page itself, so its URL keeps them.
+#[component]
+pub fn TodoFilters(list_id: i64, form: GetTodosForm) -> impl IntoView {
+    view! {
+        <form
+            id="filters"
+            class="flex flex-row gap-2"
+            hx-get=format!("/lists/{list_id}")
+            hx-trigger="change, submit"
+            hx-select="#todos"
+            hx-target="#todos"
+            hx-swap="outerHTML"
+            hx-push-url="true"
+        >
+            <select name="filter">
+                {Filter::ALL
+                    .into_iter()
+                    .map(|filter| {
+                        view! {
+                            <option value=filter.value() selected=filter == form.filter>
+                                {filter.label()}
+                            </option>
+                        }
+                    })
+                    .collect_view()}
+            </select>
+            <select name="due">
+                {DueFilter::ALL
+                    .into_iter()
+                    .map(|due| {
+                        view! {
+                            <option value=due.value() selected=due == form.due>
+                                {due.label()}
+                            </option>
+                        }
+                    })
+                    .collect_view()}
+            </select>
+            <select name="priority">
+                <option value="">Any priority</option>
+                {Priority::ALL
+                    .into_iter()
+                    .map(|priority| {
+                        view! {
+                            <option value=priority.value() selected=Some(priority) == form.priority>
+                                {priority.label()}
+                            </option>
+                        }
+                    })
+                    .collect_view()}
+            </select>
+            <input
+                type="search"
+                name="q"
+                placeholder="Search"
+                value=form.q
+                hx-get=format!("/lists/{list_id}")
+                hx-trigger="keyup changed delay:300ms, search"
+                hx-include="closest form"
+            />
+            <input type="search" name="tag" placeholder="Tag" value=form.tag/>
+            <label class="flex flex-row gap-1 items-center">
+                "Sort by"
+                <select name="order">
+                    {Order::ALL
+                        .into_iter()
+                        .map(|order| {
+                            view! {
+                                <option value=order.value() selected=order == form.order>
+                                    {order.label()}
+                                </option>
+                            }
+                        })
+                        .collect_view()}
+                </select>
+            </label>
+        </form>
+    }
+}
+
+/// Form adding a todo at the end of the list, or at the end of the subtasks of `parent_id`. `oob`
+/// swaps it out of band, to reset it after a todo was added.
+///
+/// The forms adding subtasks start hidden, until "Add subtask" is clicked.
+#[component]
+pub fn NewTodoForm(
+    list_id: i64,
+    #[prop(optional_no_strip)] parent_id: Option<i64>,
+    #[prop(optional)] value: String,
+    #[prop(optional)] error: Option<String>,
+    #[prop(optional)] oob: bool,
+) -> impl IntoView {
+    let hidden = parent_id.is_some() && !oob && error.is_none();
+    view! {
+        <form
+            id=new_todo_form_id(parent_id)
+            class:hidden=hidden
+            hx-post=format!("/lists/{list_id}/todos")
+            hx-target=parent_id
+                .map_or_else(|| "#todos".to_owned(), |parent_id| format!("#subtasks-{parent_id}"))
+            hx-swap="beforeend"
+            hx-swap-oob=oob.then_some("true")
+        >
+            {parent_id
+                .map(|parent_id| view! { <input type="hidden" name="parent_id" value=parent_id/> })}
+            <input
+                type="text"
+                name="content"
+                value=value
+                required
+                maxlength=validation::MAX_CONTENT_LENGTH
+                aria-invalid=error.is_some().then_some("true")
+            />
+            <button class="bg-teal-200 rounded-md p-2" type="submit">
+                {if parent_id.is_some() { "Add subtask" } else { "Add new" }}
+            </button>
+            {parent_id
+                .map(|_| {
+                    view! {
+                        <button type="button" onclick="this.form.classList.add('hidden')">
+                            Cancel
+                        </button>
+                    }
+                })}
+            {error.map(|error| view! { <p class="text-red-700">{error}</p> })}
+        </form>
+    }
+}
+
+/// Id of the [`NewTodoForm`] adding subtasks to `parent_id`, or todos to the list.
+pub fn new_todo_form_id(parent_id: Option<i64>) -> String {
+    parent_id.map_or_else(
+        || "new-todo".to_owned(),
+        |parent_id| format!("new-subtask-{parent_id}"),
+    )
+}
+
+/// The todos as a tree of [`TodoNode`]s, each level in the order of `todos`. The todos whose
+/// parent is not part of `todos`, filtered out, show at the top level. The subtasks of `sortable`
+/// trees can be dragged, see [`GetTodosForm::sortable`].
+#[component]
+pub fn TodoTree(
+    list_id: i64,
+    todos: Vec<(i64, Todo)>,
+    #[prop(optional)] readonly: bool,
+    #[prop(optional)] sortable: bool,
+    #[prop(optional)] editors: HashMap<i64, String>,
+    #[prop(optional)] terms: Terms,
+) -> impl IntoView {
+    let ids: HashSet<i64> = todos.iter().map(|(id, _)| *id).collect();
+    let mut roots = Vec::new();
+    let mut subtasks: HashMap<i64, Vec<(i64, Todo)>> = HashMap::new();
+    for (id, todo) in todos {
+        match todo.parent_id.filter(|parent_id| ids.contains(parent_id)) {
+            Some(parent_id) => subtasks.entry(parent_id).or_default().push((id, todo)),
+            None => roots.push((id, todo)),
+        }
+    }
+
+    let mut tree = Tree {
+        list_id,
+        readonly,
+        sortable,
+        terms,
+        subtasks,
+        editors,
+    };
+    roots
+        .into_iter()
+        .map(|(id, todo)| tree.node(id, todo))
+        .collect_view()
+}
+
+/// A page of the todos, followed by the row loading the next one when it is scrolled into view.
+#[component]
+pub fn TodoPage(
+    list_id: i64,
+    todos: Vec<(i64, Todo)>,
+    next: Option<Cursor>,
+    #[prop(optional)] readonly: bool,
+    #[prop(optional)] sortable: bool,
+    #[prop(optional)] editors: HashMap<i64, String>,
+    #[prop(optional)] terms: Terms,
+) -> impl IntoView {
+    view! {
+        <TodoTree list_id todos readonly sortable editors terms/>
+        {next
+            .map(|after| {
+                let after = serde_json::to_string(&after).unwrap_or_default();
+                // The filters and order of the list page give the same todos as the first page
+                view! {
+                    <div
+                        id="more-todos"
+                        class="text-gray-500"
+                        hx-get=format!("/lists/{list_id}/todos")
+                        hx-vals=serde_json::json!({ "after": after }).to_string()
+                        hx-include="#filters"
+                        hx-trigger="revealed"
+                        hx-target="this"
+                        hx-swap="outerHTML"
+                    >
+                        "Loading more todos…"
+                    </div>
+                }
+            })}
+    }
+}
+
+/// What a [`TodoTree`] renders its nodes with, the subtasks of each todo and its editor being
+/// taken as the node is rendered.
+struct Tree {
+    list_id: i64,
+    readonly: bool,
+    sortable: bool,
+    terms: Terms,
+    subtasks: HashMap<i64, Vec<(i64, Todo)>>,
+    editors: HashMap<i64, String>,
+}
+
+impl Tree {
+    fn node(&mut self, id: i64, todo: Todo) -> View {
+        let children = self
+            .subtasks
+            .remove(&id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(id, todo)| self.node(id, todo))
+            .collect_view();
+        let Tree {
+            list_id,
+            readonly,
+            sortable,
+            ..
+        } = *self;
+        let editor = self.editors.remove(&id);
+        let terms = self.terms.clone();
+        view! { <TodoNode list_id id todo readonly sortable editor terms subtasks=children/> }
+            .into_view()
+    }
+}
+
+/// A [`Todo`] row followed by its subtasks and the form adding one. The subtasks of `sortable`
+/// nodes can be dragged, see [`GetTodosForm::sortable`].
+#[component]
+pub fn TodoNode(
+    list_id: i64,
+    id: i64,
+    todo: Todo,
+    #[prop(optional)] readonly: bool,
+    #[prop(optional)] sortable: bool,
+    #[prop(optional_no_strip)] editor: Option<String>,
+    #[prop(optional)] terms: Terms,
+    #[prop(optional)] subtasks: View,
+) -> impl IntoView {
+    let sortable = sortable && !readonly;
+    view! {
+        <div id=format!("node-{id}") class="todo-node" data-id=id>
+            <Todo list_id id todo readonly editor terms/>
+            // Editors reorder the subtasks by dragging them, see `reorder_todos`
+            <div
+                id=format!("subtasks-{id}")
+                class="ml-8"
+                class:sortable=sortable
+                hx-post=sortable.then(|| format!("/lists/{list_id}/todos/reorder"))
+                hx-trigger=sortable.then_some("end consume")
+            >
+                {subtasks}
+            </div>
+            {(!readonly).then(|| view! { <NewTodoForm list_id parent_id=Some(id)/> })}
+        </div>
+    }
+}
+
+/// Rows of the todos changed along with another one, swapped out of band.
+#[component]
+fn OobRows(list_id: i64, rows: Vec<(i64, Todo)>, editors: HashMap<i64, String>) -> impl IntoView {
+    let mut editors = editors;
+    rows.into_iter()
+        .map(|(id, todo)| {
+            let editor = editors.remove(&id);
+            view! { <Todo list_id id todo editor oob=true/> }
+        })
+        .collect_view()
+}
+
+/// Progress of the subtasks of a [`Todo`] row, clicking it shows or hides them.
+#[component]
+fn SubtasksToggle(id: i64, progress: Progress) -> impl IntoView {
+    (progress.total > 0).then(|| {
+        view! {
+            <button
+                type="button"
+                class="text-sm"
+                title="Show or hide the subtasks"
+                onclick=format!(
+                    "document.getElementById('subtasks-{id}').classList.toggle('hidden')",
+                )
+            >
+                {format!("{}/{} done", progress.done, progress.total)}
+            </button>
+        }
+    })
+}
+
+/// Priority of a [`Todo`] row, left out when normal.
+#[component]
+fn PriorityBadge(priority: Priority) -> impl IntoView {
+    (priority != Priority::Normal).then(|| {
+        view! {
+            <span
+                class="text-sm rounded-md px-2"
+                class:bg-red-200=priority == Priority::High
+                class:bg-gray-200=priority == Priority::Low
+            >
+                {priority.label()}
+            </span>
+        }
+    })
+}
+
+/// Tags of a [`Todo`] row, clicking one filters the list on it.
+#[component]
+fn TagChips(tags: BTreeSet<String>, terms: Terms) -> impl IntoView {
+    view! {
+        <div class="flex flex-row gap-1">
+            {tags
+                .into_iter()
+                .map(|tag| {
+                    view! {
+                        <button
+                            type="button"
+                            class="text-sm bg-sky-100 rounded-full px-2"
+                            title=format!("Show the todos tagged {tag}")
+                            data-tag=tag.clone()
+                        >
+                            "#"
+                            <Highlighted text=tag terms=terms.clone()/>
+                        </button>
+                    }
+                })
+                .collect_view()}
+        </div>
+    }
+}
+
+/// Due date of a [`Todo`] row, in the time zone it was set in.
+#[component]
+fn DueDate(due: Option<Due>, overdue: bool) -> impl IntoView {
+    due.map(|due| {
+        let time = due.time.map(|time| time.format(" %H:%M").to_string());
+        view! {
+            <p class="text-sm" class:text-red-700=overdue class:font-bold=overdue>
+                {format!(
+                    "{} {}{} {}",
+                    if overdue { "Overdue since" } else { "Due" },
+                    due.date.format("%Y-%m-%d"),
+                    time.unwrap_or_default(),
+                    due.time_zone.name(),
+                )}
+            </p>
+        }
+    })
+}
+
+/// Inline editor replacing a [`Todo`] row: Enter saves, Escape or "Cancel" restores the row.
+#[component]
+pub fn TodoEditor(
+    list_id: i64,
+    id: i64,
+    form: EditTodoForm,
+    #[prop(optional)] error: Option<String>,
+) -> impl IntoView {
+    view! {
+        <form
+            id=format!("todo-{id}")
+            class="w-full"
+            hx-patch=format!("/lists/{list_id}/todos/{id}")
+            hx-target="this"
+            hx-swap="outerHTML"
+        >
+            <hr class="w-full"/>
+            <div class="flex flex-row justify-between w-full text-xl">
+                <input type="hidden" name="version" value=form.version/>
+                <input
+                    type="text"
+                    name="content"
+                    value=form.content
+                    autofocus
+                    hx-get=format!("/lists/{list_id}/todos/{id}")
+                    hx-trigger="keyup[key=='Escape']"
+                    hx-target=format!("#todo-{id}")
+                    hx-swap="outerHTML"
+                />
+                <input type="date" name="due_date" value=form.due_date/>
+                <input type="time" name="due_time" value=form.due_time/>
+                // Filled with the time zone of the browser when the todo has no due date yet
+                <input type="hidden" name="time_zone" value=form.time_zone/>
+                <select name="priority">
+                    {Priority::ALL
+                        .into_iter()
+                        .map(|priority| {
+                            view! {
+                                <option value=priority.value() selected=priority == form.priority>
+                                    {priority.label()}
+                                </option>
+                            }
+                        })
+                        .collect_view()}
+                </select>
+                <input
+                    type="text"
+                    name="tags"
+                    placeholder="Tags"
+                    value=form.tags
+                />
+                <button type="submit">Save</button>
+                <button
+                    type="button"
+                    hx-get=format!("/lists/{list_id}/todos/{id}")
+                    hx-target=format!("#todo-{id}")
+                    hx-swap="outerHTML"
+                >
+                    Cancel
+                </button>
+            </div>
+            {error.map(|error| view! { <p class="text-red-700">{error}</p> })}
+        </form>
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::Arc;
+
+    use axum::{
+        extract::State,
+        http::{HeaderMap, HeaderValue, StatusCode},
+        response::{IntoResponse, Response},
+    };
+    use chrono::{Duration, Utc};
+
+    use super::{
+        create_todo, patch_todo, put_todo, CreateTodoForm, Due, EditTodoForm, Filter, GetTodosForm,
+        Order, Priority, SetDoneForm, Todo, PAGE_SIZE,
+    };
+    use crate::{
+        config::Config,
+        events::{ClientId, ListEvents},
+        extract::{Form, Path},
+        lists::{CurrentList, TodoList},
+        members::Role,
+        presence::Presence,
+        repository::{
+            self, MemoryTodoRepository, SqliteListRepository, SqliteMemberRepository,
+            SqliteUserRepository,
+        },
+        AppState,
+    };
+
+    /// Top-level todos, in the order they were dragged in, updated in turn.
+    fn todos(count: i64) -> Vec<(i64, Todo)> {
+        (1..=count)
+            .map(|id| {
+                let mut todo = Todo::new(format!("Todo {id}"));
+                todo.position = id as f64;
+                todo.updated_at = Utc::now() + Duration::seconds(id);
+                (id, todo)
+            })
+            .collect()
+    }
+
+    fn ids(todos: &[(i64, Todo)]) -> Vec<i64> {
+        todos.iter().map(|(id, _)| *id).collect()
+    }
+
+    fn due_at(date: &str, time: &str, time_zone: &str) -> String {
+        let due = Due {
+            date: date.parse().unwrap(),
+            time: Some(time.parse().unwrap()),
+            time_zone: time_zone.parse().unwrap(),
+        };
+        due.at().to_rfc3339()
+    }
+
+    #[test]
+    fn due_times_skipped_by_dst_move_to_the_end_of_the_gap() {
+        assert_eq!(
+            due_at("2024-03-31", "02:30:00", "Europe/Paris"),
+            "2024-03-31T01:00:00+00:00"
+        );
+        assert_eq!(
+            due_at("2024-03-10", "02:59:30", "America/New_York"),
+            "2024-03-10T07:00:00+00:00"
+        );
+        assert_eq!(
+            due_at("2024-03-31", "03:00:00", "Europe/Paris"),
+            "2024-03-31T01:00:00+00:00"
+        );
+        // Times repeated when clocks go back are the first of the two
+        assert_eq!(
+            due_at("2024-10-27", "02:30:00", "Europe/Paris"),
+            "2024-10-27T00:30:00+00:00"
+        );
+    }
+
+    #[test]
+    fn page_resumes_after_a_deleted_cursor() {
+        let form = GetTodosForm::default();
+        let mut all = todos(120);
+        let (first, next) = form.page(all.clone(), Utc::now(), None);
+        assert_eq!(ids(&first), (1..=50).collect::<Vec<_>>());
+
+        all.retain(|(id, _)| *id != 50);
+        let (second, next) = form.page(all.clone(), Utc::now(), next.as_ref());
+        assert_eq!(ids(&second), (51..=100).collect::<Vec<_>>());
+        let (third, next) = form.page(all, Utc::now(), next.as_ref());
+        assert_eq!(ids(&third), (101..=120).collect::<Vec<_>>());
+        assert!(next.is_none());
+    }
+
+    #[test]
+    fn page_resumes_after_a_filtered_out_cursor() {
+        let mut form = GetTodosForm {
+            filter: Filter::NotDone,
+            ..GetTodosForm::default()
+        };
+        let mut all = todos(120);
+        let (_, next) = form.page(all.clone(), Utc::now(), None);
+
+        for (id, todo) in &mut all {
+            todo.done = (48..=52).contains(id);
+        }
+        let (second, _) = form.page(all.clone(), Utc::now(), next.as_ref());
+        assert_eq!(second.first().map(|(id, _)| *id), Some(53));
+        assert_eq!(second.len(), PAGE_SIZE);
+
+        form.filter = Filter::All;
+        let (second, _) = form.page(all, Utc::now(), next.as_ref());
+        assert_eq!(second.first().map(|(id, _)| *id), Some(51));
+    }
+
+    #[test]
+    fn page_keeps_its_place_when_the_order_changes() {
+        let form = GetTodosForm {
+            order: Order::Updated,
+            ..GetTodosForm::default()
+        };
+        let mut all = todos(120);
+        let (first, next) = form.page(all.clone(), Utc::now(), None);
+        assert_eq!(ids(&first), (71..=120).rev().collect::<Vec<_>>());
+
+        // Updating a todo of the next page moves it to the first one, it is not loaded twice
+        let (_, todo) = &mut all[9];
+        todo.updated_at = Utc::now() + Duration::days(1);
+        let (second, _) = form.page(all, Utc::now(), next.as_ref());
+        assert_eq!(second.first().map(|(id, _)| *id), Some(70));
+        assert!(!ids(&second).contains(&10));
+        assert_eq!(second.len(), PAGE_SIZE);
+    }
+
+    /// The todos in memory, everything else in an empty database.
+    pub(crate) async fn state() -> AppState {
+        let pool = repository::connect("sqlite::memory:").await.unwrap();
+        AppState {
+            config: Arc::new(Config::from_env().unwrap()),
+            todos: Arc::new(MemoryTodoRepository::default()),
+            lists: Arc::new(SqliteListRepository::new(pool.clone())),
+            members: Arc::new(SqliteMemberRepository::new(pool.clone())),
+            users: Arc::new(SqliteUserRepository::new(pool)),
+            events: Arc::new(ListEvents::default()),
+            presence: Arc::new(Presence::default()),
+        }
+    }
+
+    pub(crate) fn list(role: Role) -> CurrentList {
+        CurrentList(TodoList {
+            id: 1,
+            name: "Todo".to_owned(),
+            role,
+        })
+    }
+
+    pub(crate) async fn body(response: Response) -> String {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    async fn create(state: &AppState, role: Role, content: &str) -> Response {
+        create_from(state, role, content, "/lists/1").await
+    }
+
+    /// Creates the todo from the list page at `url`.
+    async fn create_from(state: &AppState, role: Role, content: &str, url: &str) -> Response {
+        let form = CreateTodoForm {
+            content: content.to_owned(),
+            parent_id: None,
+        };
+        let mut headers = HeaderMap::new();
+        let url = format!("http://localhost{url}");
+        headers.insert("HX-Current-URL", HeaderValue::from_str(&url).unwrap());
+        create_todo(
+            State(state.clone()),
+            list(role),
+            ClientId(None),
+            headers,
+            Form(form),
+        )
+        .await
+        .into_response()
+    }
+
+    #[tokio::test]
+    async fn create_todo_stores_and_renders_it() {
+        let state = state().await;
+        let response = create(&state, Role::Editor, "  Buy milk ").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(body(response).await.contains("Buy milk"));
+
+        let todos = state.todos.list(1).await.unwrap();
+        let contents: Vec<_> = todos
+            .iter()
+            .map(|(_, todo)| todo.content.as_str())
+            .collect();
+        assert_eq!(contents, ["Buy milk"]);
+    }
+
+    #[tokio::test]
+    async fn subtasks_are_sortable_in_the_custom_order_only() {
+        let state = state().await;
+        let response = create(&state, Role::Editor, "Trip").await;
+        assert!(body(response).await.contains("sortable"));
+
+        for url in [
+            "/lists/1?order=Priority",
+            "/lists/1?filter=Done",
+            "/lists/1?q=pack",
+        ] {
+            let response = create_from(&state, Role::Editor, "Trip", url).await;
+            assert!(!body(response).await.contains("sortable"), "{url}");
+        }
+    }
+
+    #[tokio::test]
+    async fn viewers_cannot_create_todos() {
+        let state = state().await;
+        let response = create(&state, Role::Viewer, "Buy milk").await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(state.todos.list(1).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn put_todo_completes_the_subtasks() {
+        let state = state().await;
+        let parent_id = state
+            .todos
+            .create(1, Todo::new("Trip".to_owned()))
+            .await
+            .unwrap();
+        let subtask_id = state
+            .todos
+            .create(
+                1,
+                Todo {
+                    parent_id: Some(parent_id),
+                    ..Todo::new("Pack".to_owned())
+                },
+            )
+            .await
+            .unwrap();
+
+        let form = SetDoneForm {
+            done: true,
+            version: 0,
+        };
+        let path = Path((1, parent_id));
+        let response = put_todo(
+            State(state.clone()),
+            list(Role::Editor),
+            path,
+            ClientId(None),
+            Form(form),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let subtask = state.todos.get(1, subtask_id).await.unwrap().unwrap();
+        assert!(subtask.done);
+
+        // The version the form was based on is gone now
+        let form = SetDoneForm {
+            done: false,
+            version: 0,
+        };
+        let path = Path((1, parent_id));
+        let response = put_todo(
+            State(state.clone()),
+            list(Role::Editor),
+            path,
+            ClientId(None),
+            Form(form),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        let form = SetDoneForm {
+            done: false,
+            version: 1,
+        };
+        let path = Path((1, subtask_id + 1));
+        let response = put_todo(
+            State(state.clone()),
+            list(Role::Editor),
+            path,
+            ClientId(None),
+            Form(form),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn invalid_edits_are_rendered_back_as_submitted() {
+        let state = state().await;
+        let id = state
+            .todos
+            .create(1, Todo::new("Buy milk".to_owned()))
+            .await
+            .unwrap();
+
+        let form = EditTodoForm {
+            content: " ".to_owned(),
+            version: 0,
+            due_date: "2024-06-01".to_owned(),
+            due_time: "09:30".to_owned(),
+            time_zone: "Europe/Paris".to_owned(),
+            priority: Priority::High,
+            tags: "home, errands".to_owned(),
+        };
+        let response = patch_todo(
+            State(state.clone()),
+            list(Role::Editor),
+            Path((1, id)),
+            ClientId(None),
+            Form(form),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = body(response).await;
+        for value in ["2024-06-01", "09:30", "Europe/Paris", "home, errands"] {
+            assert!(body.contains(&format!(r#"value="{value}""#)), "{value}");
+        }
+        assert!(body.contains(r#"value="high" selected"#));
+
+        let todo = state.todos.get(1, id).await.unwrap().unwrap();
+        assert_eq!(todo.content, "Buy milk");
+    }
+}
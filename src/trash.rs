@@ -0,0 +1,258 @@
+//! The trash of each list: deleted todos stay there, and can be restored, until a background task
+//! purges them once they have been deleted for longer than the retention period.
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    extract::State,
+    response::{Html, IntoResponse},
+};
+use chrono::{DateTime, Utc};
+use leptos::*;
+
+use crate::{
+    errors::ApplicationError,
+    events::{ClientId, TodoChange},
+    extract::Path,
+    footer::TodoFooter,
+    lists::CurrentList,
+    members::Role,
+    repository::TodoRepository,
+    todos::{ReloadTodos, Todo},
+    AppState,
+};
+
+/// A todo in the trash. Its subtasks deleted along with it are restored with it.
+#[derive(Debug, Clone)]
+pub struct TrashedTodo {
+    pub id: i64,
+    pub todo: Todo,
+    /// Direct subtasks deleted along with the todo.
+    pub subtasks: i64,
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// Purges the todos deleted for longer than `retention` every `interval`, until the process
+/// exits.
+pub async fn run(todos: Arc<dyn TodoRepository>, interval: Duration, retention: Duration) {
+    let mut ticks = tokio::time::interval(interval);
+    loop {
+        ticks.tick().await;
+        let before = Utc::now().timestamp() - retention.as_secs() as i64;
+        match todos.purge(before).await {
+            Ok(0) => {}
+            Ok(purged) => tracing::info!("purged {purged} todos from the trash"),
+            Err(e) => tracing::error!("cannot purge the trash: {e:#}"),
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/lists/{list_id}/trash",
+    params(("list_id" = i64, Path, description = "Id of the list")),
+    responses(
+        (status = 200, description = "The trash panel, listing the deleted todos", content_type = "text/html"),
+        (status = 404, description = "No such list", content_type = "text/html")
+    )
+)]
+pub async fn get_trash(
+    State(state): State<AppState>,
+    CurrentList(list): CurrentList,
+) -> Result<impl IntoResponse, ApplicationError> {
+    let todos = state.todos.trash(list.id).await?;
+    let readonly = list.role < Role::Editor;
+    let retention = Duration::from_secs(state.config.trash_retention_secs);
+    Ok(Html(
+        leptos::ssr::render_to_string(move || {
+            view! { <TrashPanel list_id=list.id todos readonly retention/> }
+        })
+        .into_owned(),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{list_id}/todos/{id}/restore",
+    params(
+        ("list_id" = i64, Path, description = "Id of the list"),
+        ("id" = i64, Path, description = "Id of the todo")
+    ),
+    responses(
+        (status = 200, description = "The todo was restored, the undo toast is cleared, the footer updated and the todos reloaded out of band", content_type = "text/html"),
+        (status = 403, description = "Viewers cannot change the todos", content_type = "text/html"),
+        (status = 404, description = "No such todo in the trash", content_type = "text/html")
+    )
+)]
+pub async fn restore_todo(
+    State(state): State<AppState>,
+    CurrentList(list): CurrentList,
+    Path((_, id)): Path<(i64, i64)>,
+    ClientId(client): ClientId,
+) -> Result<impl IntoResponse, ApplicationError> {
+    list.require(Role::Editor)?;
+    restore(&state, list.id, id).await?;
+    state.events.publish(list.id, client, TodoChange::Reload);
+    let counts = state.todos.counts(list.id).await?;
+    Ok(Html(
+        leptos::ssr::render_to_string(move || {
+            view! {
+                <UndoToast list_id=list.id oob=true/>
+                <TodoFooter list_id=list.id counts oob=true/>
+                <ReloadTodos list_id=list.id/>
+            }
+        })
+        .into_owned(),
+    ))
+}
+
+/// Takes the todo out of the trash. Restoring a todo not done reopens its ancestors, as adding it
+/// would.
+pub async fn restore(state: &AppState, list_id: i64, id: i64) -> Result<Todo, ApplicationError> {
+    state
+        .todos
+        .restore(list_id, id)
+        .await?
+        .ok_or(ApplicationError::NotFound)
+}
+
+/// `duration` in days, or in hours under a day, rounded up.
+fn period(duration: Duration) -> String {
+    let hours = duration.as_secs().div_ceil(60 * 60);
+    match (hours, hours.div_ceil(24)) {
+        (1, _) => "1 hour".to_owned(),
+        (hours, _) if hours < 24 => format!("{hours} hours"),
+        (_, 1) => "1 day".to_owned(),
+        (_, days) => format!("{days} days"),
+    }
+}
+
+/// The deleted todos of the list, each with a button to restore it for editors.
+#[component]
+pub fn TrashPanel(
+    list_id: i64,
+    todos: Vec<TrashedTodo>,
+    #[prop(optional)] readonly: bool,
+    retention: Duration,
+) -> impl IntoView {
+    view! {
+        <section id="trash" class="flex flex-col gap-2">
+            <div class="flex flex-row justify-between items-center">
+                <h2 class="text-xl">Trash</h2>
+                <button type="button" onclick="this.closest('#trash').replaceChildren()">
+                    Close
+                </button>
+            </div>
+            <p class="text-sm text-gray-500">
+                {format!("Deleted todos are purged after {}.", period(retention))}
+            </p>
+            {todos.is_empty().then(|| view! { <p>The trash is empty.</p> })}
+            <ul class="flex flex-col gap-1">
+                {todos
+                    .into_iter()
+                    .map(|trashed| {
+                        let subtasks = trashed.subtasks;
+                        view! {
+                            <li class="flex flex-row gap-2 justify-between items-center">
+                                <span>{trashed.todo.content}</span>
+                                {(subtasks > 0)
+                                    .then(|| {
+                                        view! {
+                                            <span class="text-sm">
+                                                {if subtasks == 1 {
+                                                    "with 1 subtask".to_owned()
+                                                } else {
+                                                    format!("with {subtasks} subtasks")
+                                                }}
+                                            </span>
+                                        }
+                                    })}
+                                <span class="text-sm text-gray-500">
+                                    {trashed.deleted_at.format("Deleted %Y-%m-%d %H:%M UTC").to_string()}
+                                </span>
+                                {(!readonly)
+                                    .then(|| {
+                                        view! {
+                                            <button
+                                                class="underline"
+                                                hx-post=format!(
+                                                    "/lists/{list_id}/todos/{}/restore",
+                                                    trashed.id,
+                                                )
+                                                hx-target="closest li"
+                                                hx-swap="delete"
+                                            >
+                                                Restore
+                                            </button>
+                                        }
+                                    })}
+                            </li>
+                        }
+                    })
+                    .collect_view()}
+            </ul>
+        </section>
+    }
+}
+
+/// Toast offering to undo the deletion of the todos `ids`, empty without any. `oob` swaps it out
+/// of band, in place of the current one.
+///
+/// Undoing restores the todos through [`bulk_action`](crate::bulk::bulk_action), which renders
+/// the todos of the list page again.
+#[component]
+pub fn UndoToast(
+    list_id: i64,
+    #[prop(optional)] ids: Vec<i64>,
+    #[prop(optional)] oob: bool,
+) -> impl IntoView {
+    view! {
+        <div id="toast" class="fixed bottom-4 right-4" hx-swap-oob=oob.then_some("true")>
+            {(!ids.is_empty())
+                .then(|| {
+                    let message = if ids.len() == 1 {
+                        "Todo moved to the trash".to_owned()
+                    } else {
+                        format!("{} todos moved to the trash", ids.len())
+                    };
+                    let ids = ids.iter().map(i64::to_string).collect::<Vec<_>>().join(",");
+                    view! {
+                        <div
+                            role="status"
+                            class="flex flex-row gap-4 bg-gray-800 text-white rounded-md p-4"
+                        >
+                            <p>{message}</p>
+                            <button
+                                class="underline"
+                                hx-post=format!("/lists/{list_id}/todos/bulk")
+                                hx-vals=format!(r#"{{"action": "Restore", "ids": "{ids}"}}"#)
+                                hx-include="#filters"
+                                hx-target="#todos"
+                            >
+                                Undo
+                            </button>
+                            <button onclick="this.parentElement.remove()">Dismiss</button>
+                        </div>
+                    }
+                })}
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::period;
+
+    #[test]
+    fn period_rounds_up_to_hours_under_a_day() {
+        let period = |secs| period(Duration::from_secs(secs));
+        assert_eq!(period(0), "0 hours");
+        assert_eq!(period(60), "1 hour");
+        assert_eq!(period(90 * 60), "2 hours");
+        assert_eq!(period(24 * 60 * 60), "1 day");
+        assert_eq!(period(25 * 60 * 60), "2 days");
+        assert_eq!(period(30 * 24 * 60 * 60), "30 days");
+    }
+}
@@ -0,0 +1,121 @@
+use axum::{
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use std::collections::BTreeSet;
+
+use chrono::{NaiveDate, NaiveTime};
+use chrono_tz::Tz;
+
+use crate::todos::Due;
+
+/// Longest todo content accepted, in characters.
+pub const MAX_CONTENT_LENGTH: usize = 500;
+
+/// Longest list name accepted, in characters.
+pub const MAX_LIST_NAME_LENGTH: usize = 100;
+
+/// Longest tag accepted, in characters.
+pub const MAX_TAG_LENGTH: usize = 30;
+
+/// Most tags a todo can have.
+pub const MAX_TAGS: usize = 10;
+
+/// Trims a todo content, or explains why it cannot be accepted.
+pub fn todo_content(content: &str) -> Result<String, String> {
+    single_line(content, "A todo", MAX_CONTENT_LENGTH)
+}
+
+/// Trims a list name, or explains why it cannot be accepted.
+pub fn list_name(name: &str) -> Result<String, String> {
+    single_line(name, "A list name", MAX_LIST_NAME_LENGTH)
+}
+
+/// Normalizes tags to lowercase without their leading `#`, or explains why they cannot be
+/// accepted. Blank tags are skipped.
+pub fn tags<'a>(tags: impl IntoIterator<Item = &'a str>) -> Result<BTreeSet<String>, String> {
+    let mut normalized = BTreeSet::new();
+    for tag in tags {
+        let tag = tag.trim().trim_start_matches('#').to_lowercase();
+        if tag.is_empty() {
+            continue;
+        }
+        if tag.chars().count() > MAX_TAG_LENGTH {
+            return Err(format!(
+                "A tag cannot be longer than {MAX_TAG_LENGTH} characters"
+            ));
+        }
+        if tag
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || c == ',')
+        {
+            return Err("A tag cannot contain spaces or commas".to_owned());
+        }
+        normalized.insert(tag);
+    }
+    if normalized.len() > MAX_TAGS {
+        return Err(format!("A todo cannot have more than {MAX_TAGS} tags"));
+    }
+    Ok(normalized)
+}
+
+/// Reads the due date fields of a form, all empty when the todo is not due, or explains why they
+/// cannot be accepted. The time zone is UTC when missing.
+pub fn due(date: &str, time: &str, time_zone: &str) -> Result<Option<Due>, String> {
+    let (date, time, time_zone) = (date.trim(), time.trim(), time_zone.trim());
+    if date.is_empty() {
+        if !time.is_empty() {
+            return Err("A due time needs a due date".to_owned());
+        }
+        return Ok(None);
+    }
+
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| "The due date must be written as YYYY-MM-DD".to_owned())?;
+    let time = match time {
+        "" => None,
+        time => Some(
+            NaiveTime::parse_from_str(time, "%H:%M")
+                .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M:%S"))
+                .map_err(|_| "The due time must be written as HH:MM".to_owned())?,
+        ),
+    };
+    let time_zone = match time_zone {
+        "" => Tz::UTC,
+        time_zone => time_zone
+            .parse()
+            .map_err(|_| format!("Unknown time zone {time_zone}"))?,
+    };
+    Ok(Some(Due {
+        date,
+        time,
+        time_zone,
+    }))
+}
+
+fn single_line(value: &str, what: &str, max_length: usize) -> Result<String, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(format!("{what} cannot be empty"));
+    }
+    if value.chars().count() > max_length {
+        return Err(format!(
+            "{what} cannot be longer than {max_length} characters"
+        ));
+    }
+    if value.chars().any(char::is_control) {
+        return Err(format!("{what} cannot contain control characters"));
+    }
+    Ok(value.to_owned())
+}
+
+/// 422 response replacing the form identified by `target` with `form`, its re-rendered version
+/// showing the field errors.
+pub fn invalid_form(target: &str, form: String) -> Response {
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        [("HX-Retarget", target), ("HX-Reswap", "outerHTML")],
+        Html(form),
+    )
+        .into_response()
+}